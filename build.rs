@@ -0,0 +1,20 @@
+use std::process::Command;
+
+fn main() {
+    // Expose the target triple, which cargo only gives to build scripts.
+    let target = std::env::var("TARGET").unwrap_or_default();
+    println!("cargo:rustc-env=PFM_TARGET={}", target);
+
+    // Source tarballs and nix builds have no .git; the hash is optional.
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=PFM_GIT_HASH={}", hash);
+    }
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1,128 @@
+use crate::env_file::InvalidLine;
+
+/// One `host ports [options]` line from a file for `pfm add-file`, split into
+/// the words that would follow `pfm add` on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddLine {
+    /// 1-based line number, for messages
+    pub line: usize,
+    pub words: Vec<String>,
+}
+
+/// Split every non-blank, non-comment line into words. A leading `pfm add`
+/// is dropped, so `pfm show-cmd` output can be pasted in as is.
+pub fn parse(contents: &str) -> (Vec<AddLine>, Vec<InvalidLine>) {
+    let mut lines = Vec::new();
+    let mut invalid = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match split_words(line) {
+            Ok(mut words) => {
+                if words.first().is_some_and(|w| w == "pfm") {
+                    words.remove(0);
+                }
+                if words.first().is_some_and(|w| w == "add") {
+                    words.remove(0);
+                }
+                lines.push(AddLine { line: line_number, words });
+            }
+            Err(reason) => invalid.push(InvalidLine { line: line_number, reason }),
+        }
+    }
+
+    (lines, invalid)
+}
+
+/// Split `line` into words the way a POSIX shell would for plain arguments:
+/// whitespace separates words, single quotes are literal, and double quotes
+/// and backslashes escape.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err("line ends with a backslash".to_string()),
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lines_like_a_shell_and_reports_bad_quoting() {
+        let contents = "\
+# staging environment
+db.internal 5432 --tag db
+
+pfm add web 8080:80 --comment 'admin UI' -o \"ProxyJump=jump host\"
+bastion 6379 --comment \"it's unterminated
+";
+
+        let (lines, invalid) = parse(contents);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line, 2);
+        assert_eq!(lines[0].words, ["db.internal", "5432", "--tag", "db"]);
+        assert_eq!(lines[1].words, ["web", "8080:80", "--comment", "admin UI", "-o", "ProxyJump=jump host"]);
+        assert_eq!(invalid, [InvalidLine { line: 5, reason: "unterminated double quote".to_string() }]);
+        assert_eq!(split_words(r"a\ b '' c").unwrap(), ["a b", "", "c"]);
+    }
+}
@@ -0,0 +1,50 @@
+use std::io;
+
+use clap::Command;
+use clap_complete::{generate, Shell};
+
+/// Subcommands whose positional arguments accept a forward index or id.
+const ID_COMMANDS: &str = "delete logs log edit health ping rename enable disable show-cmd duplicate clone connect";
+/// Subcommands whose positional argument is a forward index only.
+const INDEX_COMMANDS: &str = "show open move systemd-unit launchd-plist";
+
+/// Print clap's static completions for `shell`, followed by a snippet that asks
+/// `pfm list-completions` for the current forwards where the shell supports it.
+pub fn generate_completions(shell: Shell, cmd: &mut Command) {
+    generate(shell, cmd, "pfm", &mut io::stdout());
+
+    match shell {
+        Shell::Bash => print!("{}", bash_snippet()),
+        Shell::Fish => print!("{}", fish_snippet()),
+        _ => {}
+    }
+}
+
+fn bash_snippet() -> String {
+    format!(
+        r#"
+_pfm_forwards() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" kind=""
+    case " {ids} " in *" ${{COMP_WORDS[1]}} "*) kind=ids ;; esac
+    case " {indices} " in *" ${{COMP_WORDS[1]}} "*) kind=indices ;; esac
+    if [[ -n "$kind" && "$cur" != -* && $COMP_CWORD -ge 2 ]]; then
+        COMPREPLY=( $(compgen -W "$(pfm list-completions "$kind" 2>/dev/null)" -- "$cur") )
+        return 0
+    fi
+    _pfm "$@"
+}}
+complete -F _pfm_forwards -o bashdefault -o default pfm
+"#,
+        ids = ID_COMMANDS,
+        indices = INDEX_COMMANDS,
+    )
+}
+
+fn fish_snippet() -> String {
+    format!(
+        "complete -c pfm -n \"__fish_seen_subcommand_from {ids}\" -f -a \"(pfm list-completions ids 2>/dev/null)\"\n\
+         complete -c pfm -n \"__fish_seen_subcommand_from {indices}\" -f -a \"(pfm list-completions indices 2>/dev/null)\"\n",
+        ids = ID_COMMANDS,
+        indices = INDEX_COMMANDS,
+    )
+}
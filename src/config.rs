@@ -1,25 +1,423 @@
 use anyhow::{Context, Result};
+use colored::Colorize;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, sync::LazyLock};
+
+use crate::port;
+use crate::ssh::SshRetryPolicy;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortForward {
     pub id: String,
+    /// `[user@]hostname`, possibly templated with `$VAR` or `${VAR}`; variables
+    /// are expanded each time ssh is started, see [`PortForward::connect_target`]
     pub host: String,
+    /// Login user passed to ssh as `user@host`; overrides a user in `host`
+    #[serde(default)]
+    pub user: Option<String>,
     pub local_port: u16,
     pub remote_port: u16,
+    /// Local port originally asked for, when `add` had to remap to `local_port`
+    /// because it was busy; restarts move back to it once it is free
+    #[serde(default)]
+    pub requested_local_port: Option<u16>,
+    /// Host the ssh server connects to for the remote end; `None` means the server itself
+    #[serde(default)]
+    pub remote_host: Option<String>,
     pub pid: Option<u32>,
+    /// Port of the ssh server, when not the default
+    #[serde(default)]
+    pub ssh_port: Option<u16>,
+    /// Seconds ssh may spend connecting, passed as `-o ConnectTimeout`
+    #[serde(default)]
+    pub connect_timeout: Option<u64>,
+    #[serde(default)]
+    pub ssh_command: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Extra `-o KEY=VALUE` options passed to ssh
+    #[serde(default)]
+    pub ssh_options: Vec<String>,
+    /// Disabled forwards stay defined but are skipped by bulk operations
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Address the local end of the tunnel listens on
+    #[serde(default = "default_bind_addr")]
+    pub bind_addr: String,
+    /// Bind the local port on all interfaces (`-o GatewayPorts=yes`)
+    #[serde(default)]
+    pub gateway: bool,
+    /// Share one ssh connection with other multiplexed forwards to the same host
+    /// (`ControlMaster=auto`), with the control socket under [`Config::control_dir`]
+    #[serde(default)]
+    pub multiplex: bool,
+    /// Capture the ssh process's stderr in a per-forward log. Forwards saved
+    /// before logging was optional were always logged, hence the default.
+    #[serde(default = "default_enabled")]
+    pub log_enabled: bool,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// File that receives the ssh process's stderr
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Unix timestamp (seconds) when the forward was added
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// Unix timestamp (seconds) when `pfm list` last saw the tunnel running
+    #[serde(default)]
+    pub last_seen_at: Option<u64>,
+    /// Unix timestamp (seconds) when `pfm watch --track-activity` last saw a
+    /// client connected to the local port
+    #[serde(default)]
+    pub last_active_at: Option<u64>,
+}
+
+/// Combine a host string with an explicit user. The explicit user wins over
+/// one embedded in `host`: `effective_target("alice@h", Some("bob"))` is `bob@h`.
+pub fn effective_target(host: &str, user: Option<&str>) -> String {
+    match user {
+        Some(user) => format!("{}@{}", user, strip_user(host)),
+        None => host.to_string(),
+    }
+}
+
+/// Replace `$NAME` and `${NAME}` in `text` with the value of that environment
+/// variable. A `$` not followed by a name is kept as is; unset variables are an error.
+pub fn expand_env(text: &str) -> Result<String> {
+    expand_with(text, |name| std::env::var(name).ok())
+}
+
+/// [`expand_env`] with the variables looked up through `lookup`.
+fn expand_with(text: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}')
+                    .with_context(|| format!("Unclosed '${{' in '{}'", text))?;
+                if end == 0 {
+                    anyhow::bail!("Empty variable name '${{}}' in '{}'", text);
+                }
+                (&braced[..end], end + 2)
+            }
+            None => {
+                let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            let value = lookup(name)
+                .ok_or_else(|| anyhow::anyhow!("Environment variable {} (in '{}') is not set", name, text))?;
+            expanded.push_str(&value);
+        }
+        rest = &after[len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// `host` without any `user@` prefix.
+pub fn strip_user(host: &str) -> &str {
+    host.rsplit_once('@').map_or(host, |(_, hostname)| hostname)
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_bind_addr() -> String {
+    DEFAULT_BIND_ADDR.to_string()
+}
+
+impl PortForward {
+    /// True if the forward carries any of `tags`, or if `tags` is empty.
+    pub fn matches_tags(&self, tags: &[String]) -> bool {
+        tags.is_empty() || tags.iter().any(|t| self.tags.contains(t))
+    }
+
+    /// The local port, prefixed with its bind address when that isn't the
+    /// default, as in ssh's `-L [bind_address:]port:host:hostport`.
+    pub fn local_end(&self) -> String {
+        if self.bind_addr == DEFAULT_BIND_ADDR {
+            self.local_port.to_string()
+        } else if self.bind_addr.contains(':') {
+            format!("[{}]:{}", self.bind_addr, self.local_port)
+        } else {
+            format!("{}:{}", self.bind_addr, self.local_port)
+        }
+    }
+
+    /// Address to connect to in order to use the tunnel.
+    pub fn local_addr(&self) -> SocketAddr {
+        SocketAddr::new(port::connect_addr(&self.bind_addr), self.local_port)
+    }
+
+    /// Where the ssh server sends forwarded connections: [`DEFAULT_REMOTE_HOST`] unless set.
+    pub fn remote_host(&self) -> &str {
+        self.remote_host.as_deref().unwrap_or(DEFAULT_REMOTE_HOST)
+    }
+
+    /// The `[user@]host` ssh connects to, with [`PortForward::user`] applied.
+    pub fn target(&self) -> String {
+        effective_target(&self.host, self.user.as_deref())
+    }
+
+    /// [`PortForward::target`] with environment variables expanded: what ssh
+    /// is actually given. Fails if a referenced variable is not set.
+    pub fn connect_target(&self) -> Result<String> {
+        expand_env(&self.target())
+    }
+
+    /// True if `command` is an ssh tunnel for this forward, i.e. `ssh ... -N ... -L <local>:...:<remote> ... <host>`.
+    pub fn matches_command(&self, command: &str) -> bool {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let Some((program, args)) = words.split_first() else {
+            return false;
+        };
+        let program = Path::new(program).file_name().and_then(|name| name.to_str());
+        if !matches!(program, Some("ssh" | "ssh.exe")) || !args.contains(&"-N") {
+            return false;
+        }
+        let local = format!("{}:", self.local_end());
+        let remote = format!(":{}", self.remote_port);
+        let forwards_port = args.windows(2).any(|pair| {
+            pair[0] == "-L" && pair[1].starts_with(&local) && pair[1].ends_with(&remote)
+        });
+        let target = self.connect_target().unwrap_or_else(|_| self.target());
+        forwards_port && args.last() == Some(&target.as_str())
+    }
+
+    /// True if the recorded pid is alive and still runs this forward's ssh tunnel.
+    /// A pid reused by an unrelated process does not count. When the command line
+    /// cannot be read, a live pid is trusted.
+    pub fn is_running(&self) -> bool {
+        let Some(pid) = self.pid else {
+            return false;
+        };
+        if !port::is_process_running(pid) {
+            return false;
+        }
+        port::process_command_line(pid).is_none_or(|command| self.matches_command(&command))
+    }
+
+    /// Forget the pid and log path, which only make sense on the machine that started the tunnel.
+    pub fn clear_runtime_state(&mut self) {
+        self.pid = None;
+        self.log_file = None;
+    }
+
+    /// Case-insensitive substring match against host, comment, and tags.
+    pub fn matches_filter(&self, pattern: &str) -> bool {
+        let pattern = pattern.to_lowercase();
+        let contains = |text: &str| text.to_lowercase().contains(&pattern);
+
+        contains(&self.host)
+            || self.comment.as_deref().is_some_and(contains)
+            || self.tags.iter().any(|t| contains(t))
+    }
 }
 
+/// Defaults applied to every forward unless overridden on the command line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub retry: SshRetryPolicy,
+    /// How many ports to try when remapping a busy local port
+    #[serde(default = "default_port_search_attempts")]
+    pub port_search_attempts: u16,
+    /// Where to look for a free port when remapping, instead of just above the busy one
+    #[serde(default)]
+    pub default_port_range: Option<port::PortRange>,
+    /// `ServerAliveInterval` (seconds) for new forwards whose ssh options don't set one
+    #[serde(default)]
+    pub server_alive_interval: Option<u64>,
+    /// `ConnectTimeout` (seconds) for new forwards that don't set one
+    #[serde(default)]
+    pub default_connect_timeout: Option<u64>,
+    /// Milliseconds to watch a new ssh process for an early exit before assuming it is alive
+    #[serde(default = "default_startup_wait_ms")]
+    pub startup_wait_ms: u64,
+}
+
+fn default_port_search_attempts() -> u16 {
+    100
+}
+
+fn default_startup_wait_ms() -> u64 {
+    3000
+}
+
+/// Keys accepted by [`Settings::get`] and [`Settings::set`].
+pub const SETTING_KEYS: &[&str] = &[
+    "retry.attempts",
+    "retry.delay_ms",
+    "port_search_attempts",
+    "startup_wait_ms",
+    "default_port_range",
+    "server_alive_interval",
+    "default_connect_timeout",
+];
+
+/// How an optional setting that is not set is shown, and how to unset one.
+pub const UNSET: &str = "none";
+
+impl Settings {
+    pub fn startup_wait(&self) -> Duration {
+        Duration::from_millis(self.startup_wait_ms)
+    }
+
+    /// The value of `key` as text; optional settings that aren't set read as [`UNSET`].
+    pub fn get(&self, key: &str) -> Result<String> {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| UNSET.to_string());
+        Ok(match key {
+            "retry.attempts" => self.retry.attempts.to_string(),
+            "retry.delay_ms" => self.retry.delay_ms.to_string(),
+            "port_search_attempts" => self.port_search_attempts.to_string(),
+            "startup_wait_ms" => self.startup_wait_ms.to_string(),
+            "default_port_range" => optional(self.default_port_range.map(|r| r.to_string())),
+            "server_alive_interval" => optional(self.server_alive_interval.map(|s| s.to_string())),
+            "default_connect_timeout" => optional(self.default_connect_timeout.map(|s| s.to_string())),
+            _ => return Err(unknown_setting(key)),
+        })
+    }
+
+    /// Parse `value` for `key` and store it. Optional settings are cleared with [`UNSET`].
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        fn number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T> {
+            value.parse().map_err(|_| anyhow::anyhow!("'{}' is not a valid number for {}", value, key))
+        }
+        let unset = value.eq_ignore_ascii_case(UNSET);
+        match key {
+            "retry.attempts" => self.retry.attempts = number(key, value)?,
+            "retry.delay_ms" => self.retry.delay_ms = number(key, value)?,
+            "port_search_attempts" => self.port_search_attempts = number(key, value)?,
+            "startup_wait_ms" => self.startup_wait_ms = number(key, value)?,
+            "default_port_range" if unset => self.default_port_range = None,
+            "default_port_range" => {
+                self.default_port_range = Some(port::parse_port_range(value).map_err(anyhow::Error::msg)?);
+            }
+            "server_alive_interval" if unset => self.server_alive_interval = None,
+            "server_alive_interval" => self.server_alive_interval = Some(number(key, value)?),
+            "default_connect_timeout" if unset => self.default_connect_timeout = None,
+            "default_connect_timeout" => self.default_connect_timeout = Some(number(key, value)?),
+            _ => return Err(unknown_setting(key)),
+        }
+        Ok(())
+    }
+}
+
+fn unknown_setting(key: &str) -> anyhow::Error {
+    anyhow::anyhow!("Unknown setting '{}'; expected one of: {}", key, SETTING_KEYS.join(", "))
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            retry: SshRetryPolicy::default(),
+            port_search_attempts: default_port_search_attempts(),
+            default_port_range: None,
+            server_alive_interval: None,
+            default_connect_timeout: None,
+            startup_wait_ms: default_startup_wait_ms(),
+        }
+    }
+}
+
+/// Write `contents` to a sibling temp file, fsync it, and rename it over
+/// `path`, so a crash mid-write never leaves a truncated file behind.
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Rename `path` to `<path>.bak`, or `<path>.bak.2`, ... if that is taken.
+fn set_aside(path: &Path) -> std::io::Result<PathBuf> {
+    let dest = (1..)
+        .map(|n| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(".bak");
+            if n > 1 {
+                name.push(format!(".{}", n));
+            }
+            PathBuf::from(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .expect("a free backup name exists");
+    fs::rename(path, &dest)?;
+    Ok(dest)
+}
+
+/// Sort key for `<unix time>[-<n>]_config.json`: by time, then by the
+/// counter added when several backups are made within one second.
+fn backup_order(path: &Path) -> (u64, u32, PathBuf) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name.trim_end_matches("_config.json");
+    let (time, counter) = stem.split_once('-').unwrap_or((stem, "1"));
+    (time.parse().unwrap_or(0), counter.parse().unwrap_or(1), path.to_path_buf())
+}
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Remote end of a forward when no other host is given: the ssh server itself.
+pub const DEFAULT_REMOTE_HOST: &str = "localhost";
+
+/// Address the local end of a forward listens on unless configured otherwise.
+pub const DEFAULT_BIND_ADDR: &str = "127.0.0.1";
+
+pub type Forwards = HashMap<String, PortForward>;
+
+static NO_FORWARDS: LazyLock<Forwards> = LazyLock::new(HashMap::new);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub forwards: HashMap<String, PortForward>
+    #[serde(default)]
+    pub settings: Settings,
+    /// Forwards keyed by profile name, then by forward id
+    #[serde(default)]
+    pub profiles: HashMap<String, Forwards>,
+    /// Config files from before profiles kept forwards at the top level;
+    /// they are moved into the default profile on load.
+    #[serde(default, rename = "forwards", skip_serializing)]
+    legacy_forwards: Forwards,
+    #[serde(skip)]
+    active_profile: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Config {
     pub fn new() -> Self {
         Config {
-            forwards: HashMap::new(),
+            settings: Settings::default(),
+            profiles: HashMap::new(),
+            legacy_forwards: HashMap::new(),
+            active_profile: DEFAULT_PROFILE.to_string(),
         }
     }
 
@@ -30,12 +428,46 @@ impl Config {
         }
         let contents = fs::read_to_string(&config_path)
             .context("Failed to read config file")?;
-        let config: Config = serde_json::from_str(&contents)
-            .context("Failed to parse config file")?;
+        match Self::from_json(&contents) {
+            Ok(config) => Ok(config),
+            Err(e) => {
+                // One bad write should not brick every command: set the file
+                // aside and carry on as if there were no config yet. The notice
+                // ignores --quiet since the user's data has just been moved.
+                let moved_to = set_aside(&config_path)
+                    .context("Failed to parse config file, and could not move it aside")?;
+                eprintln!("{}", format!(
+                    "⚠ Config file is corrupt ({}); moved it to {} and starting with an empty config. \
+                     Recover with `pfm restore <snapshot>` or by fixing the moved file.",
+                    e, moved_to.display()
+                ).yellow());
+                Ok(Config::new())
+            }
+        }
+    }
+
+    /// Parse a config document, accepting the pre-profile layout too.
+    pub fn from_json(contents: &str) -> Result<Self> {
+        let mut config: Config = serde_json::from_str(contents)?;
+
+        let legacy = std::mem::take(&mut config.legacy_forwards);
+        if !legacy.is_empty() {
+            config.profiles.entry(DEFAULT_PROFILE.to_string()).or_default().extend(legacy);
+        }
+        config.active_profile = DEFAULT_PROFILE.to_string();
 
         Ok(config)
     }
 
+    /// A copy with machine-specific state (pids, log paths) stripped, for export.
+    pub fn to_portable(&self) -> Config {
+        let mut portable = self.clone();
+        for forward in portable.profiles.values_mut().flat_map(|f| f.values_mut()) {
+            forward.clear_runtime_state();
+        }
+        portable
+    }
+
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
 
@@ -47,26 +479,155 @@ impl Config {
         let contents = serde_json::to_string_pretty(self)
             .context("Failed to serialize config")?;
 
-        fs::write(&config_path, contents)
+        write_atomically(&config_path, contents.as_bytes())
             .context("Failed to write config file")?;
         Ok(())
     }
 
-    fn config_path() -> Result<PathBuf> {
-        let config_dir = dirs::config_dir()
+    pub fn config_dir() -> Result<PathBuf> {
+        Ok(dirs::config_dir()
             .context("Could not determine config directory")?
-            .join("pfm");
+            .join("pfm"))
+    }
+
+    pub fn config_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("config.json"))
+    }
+
+    /// Directory holding the ssh control sockets of multiplexed forwards.
+    pub fn control_dir() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("control"))
+    }
+
+    /// Directory holding the snapshots written by `pfm backup`.
+    pub fn backup_dir() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("backups"))
+    }
 
-        Ok(config_dir.join("config.json"))
+    /// Copy the config file to `dest`, or to `<unix time>_config.json` in
+    /// [`Config::backup_dir`]. Returns where the copy was written.
+    pub fn backup(dest: Option<&Path>) -> Result<PathBuf> {
+        let config_path = Self::config_path()?;
+        if !config_path.exists() {
+            anyhow::bail!("Nothing to back up: {} does not exist", config_path.display());
+        }
+        let dest = match dest {
+            Some(dest) => dest.to_path_buf(),
+            None => {
+                let dir = Self::backup_dir()?;
+                fs::create_dir_all(&dir)
+                    .context("Failed to create backup directory")?;
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                (1..)
+                    .map(|n| match n {
+                        1 => dir.join(format!("{}_config.json", now)),
+                        n => dir.join(format!("{}-{}_config.json", now, n)),
+                    })
+                    .find(|path| !path.exists())
+                    .expect("a free backup name exists")
+            }
+        };
+        fs::copy(&config_path, &dest)
+            .with_context(|| format!("Failed to write backup {}", dest.display()))?;
+        Ok(dest)
+    }
+
+    /// Snapshots in [`Config::backup_dir`], oldest first.
+    pub fn list_backups() -> Result<Vec<PathBuf>> {
+        let dir = Self::backup_dir()?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut backups: Vec<PathBuf> = fs::read_dir(&dir)
+            .context("Failed to read backup directory")?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.to_string_lossy().ends_with("_config.json"))
+            .collect();
+        backups.sort_by_key(|path| backup_order(path));
+        Ok(backups)
+    }
+
+    /// Parse a backup file without installing it.
+    pub fn read_backup(source: &Path) -> Result<Config> {
+        let contents = fs::read_to_string(source)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        Self::from_json(&contents)
+            .with_context(|| format!("{} is not a valid pfm config", source.display()))
+    }
+
+    /// Replace the config file with `source` once it has been checked to parse.
+    pub fn restore(source: &Path) -> Result<Config> {
+        let config = Self::read_backup(source)?;
+        let config_path = Self::config_path()?;
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)
+                .context("Failed to create config directory")?;
+        }
+        let contents = fs::read(source)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        write_atomically(&config_path, &contents)
+            .context("Failed to write config file")?;
+        Ok(config)
+    }
+
+    /// Where the ssh stderr of the forward with `id` in the active profile is written.
+    pub fn log_path(&self, id: &str) -> Result<PathBuf> {
+        Self::profile_log_path(&self.active_profile, id)
+    }
+
+    /// Where the ssh stderr of the forward with `id` in `profile` is written.
+    pub fn profile_log_path(profile: &str, id: &str) -> Result<PathBuf> {
+        Ok(Self::config_dir()?
+            .join("logs")
+            .join(profile)
+            .join(format!("{}.log", id)))
+    }
+
+    /// Select the profile that all forward accessors operate on.
+    pub fn use_profile(&mut self, name: &str) {
+        self.active_profile = name.to_string();
+    }
+
+    pub fn active_profile(&self) -> &str {
+        &self.active_profile
+    }
+
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles.contains_key(name)
+    }
+
+    /// Forwards of the active profile.
+    pub fn forwards(&self) -> &Forwards {
+        self.profiles.get(&self.active_profile).unwrap_or(&NO_FORWARDS)
+    }
+
+    /// Forwards of the active profile, creating the profile if needed.
+    pub fn forwards_mut(&mut self) -> &mut Forwards {
+        self.profiles.entry(self.active_profile.clone()).or_default()
+    }
+
+    /// Forwards across every profile, for checks on machine-wide resources like ports.
+    pub fn all_forwards(&self) -> impl Iterator<Item = &PortForward> {
+        self.profiles.values().flat_map(|forwards| forwards.values())
     }
 
     pub fn add_forward(&mut self, forward: PortForward) {
-        self.forwards.insert(forward.id.clone(), forward);
+        self.forwards_mut().insert(forward.id.clone(), forward);
     }
 
+    /// Local ports of the active profile's forwards, running or not.
+    pub fn configured_ports(&self) -> Vec<u16> {
+        self.forwards().values().map(|forward| forward.local_port).collect()
+    }
+
+    /// Forwards in index order: by host, then numerically by local and remote port.
+    /// The id is a final tie-breaker so the order is total and stable across runs.
     pub fn get_sorted_forwards(&self) -> Vec<&PortForward> {
-        let mut forwards: Vec<&PortForward> = self.forwards.values().collect();
-        forwards.sort_by_key(|f| &f.id);
+        let mut forwards: Vec<&PortForward> = self.forwards().values().collect();
+        forwards.sort_by_key(|f| (&f.host, f.local_port, f.remote_port, &f.id));
         forwards
     }
 
@@ -74,6 +635,14 @@ impl Config {
         self.get_sorted_forwards().get(index).copied()
     }
 
+    /// Look up a forward by list index or, failing that, by id.
+    pub fn resolve_forward(&self, index_or_id: &str) -> Option<&PortForward> {
+        match index_or_id.parse::<usize>() {
+            Ok(index) => self.get_forward_by_index(index),
+            Err(_) => self.forwards().get(index_or_id),
+        }
+    }
+
     // pub fn remove_forward_by_index(&mut self, index: usize) -> Option<PortForward> {
     //     let id = self.get_forward_by_index(index)?.id.clone();
     //     self.remove_forward(&id)
@@ -84,6 +653,197 @@ impl Config {
     // }
 
     pub fn remove_forward(&mut self, id: &str) -> Option<PortForward> {
-        self.forwards.remove(id)
+        self.profiles.get_mut(&self.active_profile)?.remove(id)
+    }
+
+    /// Structural problems across all profiles, one message per problem.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut by_port: HashMap<u16, Vec<&str>> = HashMap::new();
+
+        let mut profiles: Vec<_> = self.profiles.iter().collect();
+        profiles.sort_by_key(|(name, _)| *name);
+
+        for (profile, forwards) in profiles {
+            let mut entries: Vec<_> = forwards.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+
+            for (key, forward) in entries {
+                let label = format!("{}/{}", profile, key);
+                if forward.id.is_empty() {
+                    problems.push(format!("{}: empty id", label));
+                } else if forward.id != *key {
+                    problems.push(format!("{}: id '{}' does not match its key", label, forward.id));
+                }
+                if forward.host.trim().is_empty() {
+                    problems.push(format!("{}: empty host", label));
+                }
+                if forward.local_port == 0 {
+                    problems.push(format!("{}: local_port must be in 1..=65535", label));
+                }
+                if forward.remote_port == 0 {
+                    problems.push(format!("{}: remote_port must be in 1..=65535", label));
+                }
+                by_port.entry(forward.local_port).or_default().push(&forward.id);
+            }
+        }
+
+        let mut shared: Vec<_> = by_port.into_iter().filter(|(_, ids)| ids.len() > 1).collect();
+        shared.sort_by_key(|(port, _)| *port);
+        for (port, ids) in shared {
+            problems.push(format!("{}: local_port {} is shared", ids.join(", "), port));
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_top_level_forwards_move_into_default_profile() {
+        let json = r#"{"forwards": {"h_80_80": {
+            "id": "h_80_80", "host": "h", "local_port": 80, "remote_port": 80, "pid": null
+        }}}"#;
+
+        let config = Config::from_json(json).unwrap();
+
+        assert_eq!(config.active_profile(), DEFAULT_PROFILE);
+        assert_eq!(config.forwards()["h_80_80"].bind_addr, DEFAULT_BIND_ADDR);
+    }
+
+    #[test]
+    fn settings_round_trip_through_get_and_set() {
+        let mut settings = Settings::default();
+        for key in SETTING_KEYS {
+            let value = settings.get(key).unwrap();
+            settings.set(key, &value).unwrap();
+        }
+
+        settings.set("default_port_range", "20000-20999").unwrap();
+        settings.set("server_alive_interval", "30").unwrap();
+        assert_eq!(settings.get("default_port_range").unwrap(), "20000-20999");
+        assert_eq!(settings.server_alive_interval, Some(30));
+        settings.set("server_alive_interval", "none").unwrap();
+        assert_eq!(settings.get("server_alive_interval").unwrap(), UNSET);
+
+        assert!(settings.set("retry.attempts", "many").is_err());
+        assert!(settings.set("colour", "blue").is_err());
+        assert!(settings.get("colour").is_err());
+    }
+
+    #[test]
+    fn set_aside_never_overwrites_an_earlier_backup() {
+        let dir = std::env::temp_dir().join(format!("pfm-set-aside-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+
+        fs::write(&path, "first").unwrap();
+        assert_eq!(set_aside(&path).unwrap(), dir.join("config.json.bak"));
+        fs::write(&path, "second").unwrap();
+        assert_eq!(set_aside(&path).unwrap(), dir.join("config.json.bak.2"));
+
+        assert_eq!(fs::read_to_string(dir.join("config.json.bak")).unwrap(), "first");
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backups_sort_by_time_then_counter() {
+        let mut backups: Vec<PathBuf> = ["20_config.json", "100-2_config.json", "100_config.json"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        backups.sort_by_key(|path| backup_order(path));
+
+        assert_eq!(backups, ["20_config.json", "100_config.json", "100-2_config.json"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn sorted_forwards_order_ports_numerically_within_a_host() {
+        let json = r#"{"profiles": {"default": {
+            "b_10_10": {"id": "b_10_10", "host": "b", "local_port": 10, "remote_port": 10, "pid": null},
+            "a_10_10": {"id": "a_10_10", "host": "a", "local_port": 10, "remote_port": 10, "pid": null},
+            "a_2_2": {"id": "a_2_2", "host": "a", "local_port": 2, "remote_port": 2, "pid": null},
+            "a_2_1": {"id": "a_2_1", "host": "a", "local_port": 2, "remote_port": 1, "pid": null}
+        }}}"#;
+
+        let config = Config::from_json(json).unwrap();
+        let ids: Vec<&str> = config.get_sorted_forwards().iter().map(|f| f.id.as_str()).collect();
+
+        assert_eq!(ids, ["a_2_1", "a_2_2", "a_10_10", "b_10_10"]);
+    }
+
+    #[test]
+    fn matches_command_recognises_only_this_forwards_tunnel() {
+        let json = r#"{"profiles": {"default": {
+            "u_at_h_8080_80": {"id": "u_at_h_8080_80", "host": "u@h", "local_port": 8080, "remote_port": 80, "pid": null}
+        }}}"#;
+        let config = Config::from_json(json).unwrap();
+        let forward = &config.forwards()["u_at_h_8080_80"];
+
+        assert!(forward.matches_command("ssh -N -L 8080:localhost:80 u@h"));
+        assert!(forward.matches_command("/usr/bin/ssh -N -L 8080:localhost:80 -p 2222 u@h"));
+        assert!(!forward.matches_command("ssh -N -L 8081:localhost:80 u@h"));
+        assert!(!forward.matches_command("ssh -N -L 8080:localhost:80 other"));
+        assert!(!forward.matches_command("vim -N -L 8080:localhost:80 u@h"));
+        assert!(!forward.matches_command("[ssh] <defunct>"));
+
+        let mut ipv6 = forward.clone();
+        ipv6.bind_addr = "::1".to_string();
+        assert_eq!(ipv6.local_end(), "[::1]:8080");
+        assert!(ipv6.matches_command("ssh -N -L [::1]:8080:localhost:80 u@h"));
+        assert!(!ipv6.matches_command("ssh -N -L 8080:localhost:80 u@h"));
+    }
+
+    #[test]
+    fn expand_env_replaces_set_variables_only() {
+        let lookup = |name: &str| match name {
+            "USER" => Some("deploy".to_string()),
+            "HOST" => Some("staging.example.com".to_string()),
+            _ => None,
+        };
+        let expand = |text: &str| expand_with(text, lookup);
+
+        assert_eq!(expand("$USER@${HOST}").unwrap(), "deploy@staging.example.com");
+        assert_eq!(expand("plain-host").unwrap(), "plain-host");
+        assert_eq!(expand("cost$ 5$").unwrap(), "cost$ 5$");
+        assert!(expand("${UNSET}").unwrap_err().to_string().contains("UNSET"));
+        assert!(expand("${HOST").is_err());
+        assert!(expand("${}").is_err());
+
+        // Only reads the environment, which is safe alongside other test threads.
+        let json = r#"{"id":"t","host":"${PFM_TEST_EXPAND_UNSET}",
+                       "local_port":8080,"remote_port":80,"pid":null}"#;
+        let forward: PortForward = serde_json::from_str(json).unwrap();
+        assert!(forward.connect_target().is_err());
+    }
+
+    #[test]
+    fn explicit_user_overrides_user_in_host() {
+        assert_eq!(effective_target("h", None), "h");
+        assert_eq!(effective_target("alice@h", None), "alice@h");
+        assert_eq!(effective_target("h", Some("bob")), "bob@h");
+        assert_eq!(effective_target("alice@h", Some("bob")), "bob@h");
+    }
+
+    #[test]
+    fn validate_reports_each_problem_with_its_id() {
+        let json = r#"{"profiles": {"default": {
+            "a": {"id": "a", "host": "", "local_port": 0, "remote_port": 80, "pid": null},
+            "b": {"id": "x", "host": "h", "local_port": 9000, "remote_port": 80, "pid": null},
+            "c": {"id": "c", "host": "h", "local_port": 9000, "remote_port": 80, "pid": null}
+        }}}"#;
+
+        let problems = Config::from_json(json).unwrap().validate();
+
+        assert_eq!(problems, [
+            "default/a: empty host",
+            "default/a: local_port must be in 1..=65535",
+            "default/b: id 'x' does not match its key",
+            "x, c: local_port 9000 is shared",
+        ]);
     }
 }
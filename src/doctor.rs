@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::Result;
+use colored::Colorize;
+
+use pfm::config::{Config, PortForward};
+use pfm::{notify, port, ssh};
+
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn record(&mut self, status: Status, message: impl AsRef<str>) {
+        let label = match status {
+            Status::Pass => "PASS".green().bold(),
+            Status::Warn => "WARN".yellow().bold(),
+            Status::Fail => {
+                self.failures += 1;
+                "FAIL".red().bold()
+            }
+        };
+        println!("  [{}] {}", label, message.as_ref());
+    }
+
+    /// Suggest how to fix the check that was just recorded.
+    fn hint(&self, text: impl AsRef<str>) {
+        println!("         {} {}", "hint:".dimmed(), text.as_ref().dimmed());
+    }
+}
+
+pub fn run() -> Result<()> {
+    let mut report = Report { failures: 0 };
+
+    check_ssh(&mut report);
+    check_curl(&mut report);
+    check_config_dir(&mut report)?;
+    if let Some(config) = check_config_file(&mut report)? {
+        check_pids(&mut report, &config);
+        check_forward_ports(&mut report, &config);
+        check_duplicate_ports(&mut report, &config);
+    }
+
+    if report.failures > 0 {
+        anyhow::bail!("{} check(s) failed", report.failures);
+    }
+    Ok(())
+}
+
+fn check_ssh(report: &mut Report) {
+    match ssh::find_ssh_binary() {
+        Some(path) => report.record(Status::Pass, format!("ssh found at {}", path.display())),
+        None => {
+            report.record(Status::Fail, "ssh not found in $PATH");
+            report.hint("Install an OpenSSH client (e.g. `apt install openssh-client`) or add its directory to PATH");
+        }
+    }
+}
+
+fn check_curl(report: &mut Report) {
+    match notify::find_curl_binary() {
+        Some(path) => report.record(Status::Pass, format!("curl found at {}", path.display())),
+        None => {
+            report.record(Status::Warn, "curl not found in $PATH; `pfm watch --webhook-url` needs it");
+            report.hint("Install curl or add its directory to PATH");
+        }
+    }
+}
+
+fn check_config_dir(report: &mut Report) -> Result<()> {
+    let config_path = Config::config_path()?;
+    let Some(dir) = config_path.parent() else {
+        report.record(Status::Fail, "Config path has no parent directory");
+        return Ok(());
+    };
+
+    let probe = dir.join(".pfm-doctor-probe");
+    let writable = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe));
+
+    match writable {
+        Ok(()) => report.record(Status::Pass, format!("Config directory {} is writable", dir.display())),
+        Err(e) => {
+            report.record(Status::Fail, format!("Config directory {} is not writable: {}", dir.display(), e));
+            report.hint("Fix its ownership/permissions, or set XDG_CONFIG_HOME to a writable directory");
+        }
+    }
+    Ok(())
+}
+
+fn check_config_file(report: &mut Report) -> Result<Option<Config>> {
+    let config_path = Config::config_path()?;
+    if !config_path.exists() {
+        report.record(Status::Pass, format!("No config file yet at {}", config_path.display()));
+        return Ok(None);
+    }
+
+    let contents = match fs::read_to_string(&config_path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            report.record(Status::Fail, format!("Cannot read {}: {}", config_path.display(), e));
+            report.hint("Check the file's permissions");
+            return Ok(None);
+        }
+    };
+
+    match serde_json::from_str::<Config>(&contents) {
+        Ok(_) => {
+            report.record(Status::Pass, format!("Config file {} is valid", config_path.display()));
+            Ok(Some(Config::load()?))
+        }
+        Err(e) => {
+            report.record(Status::Fail, format!("Config file {} is invalid: {}", config_path.display(), e));
+            report.hint("Fix the JSON by hand, or move the file aside to start over");
+            Ok(None)
+        }
+    }
+}
+
+fn sorted_forwards(config: &Config) -> Vec<&PortForward> {
+    let mut forwards: Vec<&PortForward> = config.all_forwards().collect();
+    forwards.sort_by_key(|f| &f.id);
+    forwards
+}
+
+fn check_pids(report: &mut Report, config: &Config) {
+    let with_pid: Vec<&PortForward> = config.all_forwards().filter(|f| f.pid.is_some()).collect();
+    let alive = with_pid.iter().filter(|f| f.is_running()).count();
+    let dead = with_pid.len() - alive;
+
+    if dead == 0 {
+        report.record(Status::Pass, format!("{} recorded pid(s) alive", alive));
+    } else {
+        report.record(Status::Warn, format!("{} recorded pid(s) alive, {} dead", alive, dead));
+        report.hint("Relaunch them with `pfm list --restart-dead`, or drop them with `pfm cleanup`");
+    }
+}
+
+fn check_forward_ports(report: &mut Report, config: &Config) {
+    for forward in sorted_forwards(config) {
+        let running = forward.is_running();
+        let free = port::is_port_available(&forward.bind_addr, forward.local_port);
+
+        match (running, free) {
+            (true, false) => report.record(Status::Pass,
+                format!("{}: pid {} is running and port {} is listening",
+                        forward.id, forward.pid.unwrap(), forward.local_port)),
+            (false, true) => report.record(Status::Pass,
+                format!("{}: port {} is free", forward.id, forward.local_port)),
+            (true, true) => report.record(Status::Warn,
+                format!("{}: pid {} is running but port {} is not listening",
+                        forward.id, forward.pid.unwrap(), forward.local_port)),
+            (false, false) => {
+                report.record(Status::Warn,
+                    format!("{}: port {} is in use by another process", forward.id, forward.local_port));
+                report.hint(format!("Find the owner with `lsof -i :{}`", forward.local_port));
+            }
+        }
+    }
+}
+
+/// Forwards only clash when they bind the same address and port; profiles are
+/// separate setups and are not expected to run at the same time.
+fn check_duplicate_ports(report: &mut Report, config: &Config) {
+    let mut by_port: BTreeMap<(&str, &str, u16), Vec<&str>> = BTreeMap::new();
+    for (profile, forwards) in &config.profiles {
+        for forward in forwards.values() {
+            by_port.entry((profile, &forward.bind_addr, forward.local_port))
+                .or_default()
+                .push(&forward.id);
+        }
+    }
+
+    let duplicates: Vec<_> = by_port.into_iter().filter(|(_, ids)| ids.len() > 1).collect();
+    if duplicates.is_empty() {
+        report.record(Status::Pass, "No duplicate local ports");
+    }
+    for ((profile, bind_addr, port), mut ids) in duplicates {
+        ids.sort();
+        report.record(Status::Fail, format!(
+            "Local port {} on {} is shared by: {} (profile {})",
+            port, bind_addr, ids.join(", "), profile
+        ));
+        report.hint("Move one of them with `pfm edit <id> --local <port>`");
+    }
+}
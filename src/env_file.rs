@@ -0,0 +1,100 @@
+/// Prefix of the variables that describe forwards, e.g. `PFM_FORWARD_0`.
+pub const KEY_PREFIX: &str = "PFM_FORWARD_";
+
+/// A `PFM_FORWARD_<n>=<host> <ports>` line from a `.env`-style file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvForward {
+    /// 1-based line number, for messages
+    pub line: usize,
+    pub key: String,
+    pub host: String,
+    /// Port mapping as accepted by `pfm add`
+    pub ports: String,
+}
+
+/// A `PFM_FORWARD_*` line that could not be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLine {
+    pub line: usize,
+    pub reason: String,
+}
+
+/// Parse forward definitions, returning them alongside the lines that looked
+/// like forwards but were malformed. Blank lines, comments, an `export ` prefix,
+/// and unrelated variables are accepted and ignored.
+pub fn parse(contents: &str) -> (Vec<EnvForward>, Vec<InvalidLine>) {
+    let mut forwards = Vec::new();
+    let mut invalid = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        let Some(suffix) = key.strip_prefix(KEY_PREFIX) else {
+            continue;
+        };
+
+        let mut reject = |reason: String| invalid.push(InvalidLine { line: line_number, reason });
+        if suffix.is_empty() || !suffix.chars().all(|c| c.is_ascii_digit()) {
+            reject(format!("{} should end in a number, e.g. {}0", key, KEY_PREFIX));
+            continue;
+        }
+        let value = unquote(value.trim());
+        match value.split_whitespace().collect::<Vec<_>>()[..] {
+            [host, ports] => forwards.push(EnvForward {
+                line: line_number,
+                key: key.to_string(),
+                host: host.to_string(),
+                ports: ports.to_string(),
+            }),
+            _ => reject(format!("{} should be \"<host> <ports>\", got {:?}", key, value)),
+        }
+    }
+
+    (forwards, invalid)
+}
+
+/// Strip one pair of matching single or double quotes.
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_forward_lines_and_reports_malformed_ones() {
+        let contents = "\
+# tunnels for this project
+DATABASE_URL=postgres://localhost:5432/app
+PFM_FORWARD_0=db.internal 5432
+export PFM_FORWARD_1=\"admin@web 8080:80\"
+PFM_FORWARD_2=missing-ports
+PFM_FORWARD_X=web 80
+";
+
+        let (forwards, invalid) = parse(contents);
+
+        assert_eq!(forwards.len(), 2);
+        assert_eq!(forwards[0].host, "db.internal");
+        assert_eq!(forwards[0].ports, "5432");
+        assert_eq!(forwards[1].host, "admin@web");
+        assert_eq!(forwards[1].ports, "8080:80");
+        assert_eq!(forwards[1].line, 4);
+        let invalid_lines: Vec<usize> = invalid.iter().map(|i| i.line).collect();
+        assert_eq!(invalid_lines, [5, 6]);
+    }
+}
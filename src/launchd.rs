@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use crate::config::PortForward;
+use crate::ssh::SshTunnel;
+
+/// The launchd job label for `forward`, e.g. `com.pfm.db_5432_5432`.
+pub fn label(forward: &PortForward) -> String {
+    let id: String = forward.id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    format!("com.pfm.{}", id)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render a launch agent plist that keeps the forward's ssh command running.
+pub fn render_plist(forward: &PortForward, ssh_path: &Path) -> String {
+    let arguments: String = std::iter::once(ssh_path.display().to_string())
+        .chain(SshTunnel::args(forward))
+        .map(|arg| format!("        <string>{}</string>\n", escape_xml(&arg)))
+        .collect();
+
+    format!(
+        "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>KeepAlive</key>
+    <true/>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+",
+        label = escape_xml(&label(forward)),
+        arguments = arguments,
+    )
+}
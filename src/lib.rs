@@ -0,0 +1,18 @@
+//! Core of pfm: the persisted forward model, ssh tunnel management, and
+//! port/process helpers. The `pfm` binary is a thin CLI over this crate.
+
+pub mod add_file;
+pub mod config;
+pub mod env_file;
+pub mod launchd;
+pub mod manager;
+pub mod notify;
+pub mod output;
+pub mod port;
+pub mod probe;
+pub mod ssh;
+pub mod ssh_config;
+pub mod systemd;
+
+pub use config::{Config, PortForward};
+pub use ssh::{SshRetryPolicy, SshTunnel};
@@ -1,25 +1,436 @@
-use std::io;
-use clap_complete::{generate, Shell};
-use clap::{Parser, Subcommand, CommandFactory};
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use clap_complete::Shell;
+use clap::{Args, Parser, Subcommand, CommandFactory, ValueEnum};
 use colored::Colorize;
 use anyhow::{Context, Result};
 
-mod config;
-use config::{Config, PortForward};
+use pfm::manager::{self, ForwardEdit, ImportMode, NewForward};
+use pfm::notify::Notifier;
+use pfm::output::{info, warning, OutputConfig};
+use pfm::config::{effective_target, DEFAULT_BIND_ADDR, DEFAULT_PROFILE, DEFAULT_REMOTE_HOST};
+use pfm::{port, probe, ssh, Config, PortForward, SshRetryPolicy, SshTunnel};
 
-mod port;
-mod ssh;
-
-use ssh::SshTunnel;
+mod completions;
+mod doctor;
+#[cfg(feature = "tui")]
+mod tui;
 
 #[derive(Parser)]
 #[command(name = "pfm")]
 #[command(about = "Port forward manager")]
+#[command(after_help = "Exit codes:\n  \
+    0  success\n  \
+    1  error, including invalid arguments\n  \
+    2  some of the requested items failed or were not found\n  \
+    3  nothing to do (e.g. cleanup found nothing to remove)")]
 struct Cli {
+    /// Suppress informational output; only errors are printed
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Profile whose forwards commands operate on
+    #[arg(long, global = true, env = "PFM_PROFILE", default_value = DEFAULT_PROFILE)]
+    profile: String,
+
+    /// When to use colored output (auto honours NO_COLOR and whether stdout is a terminal)
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Disable colored output; same as --color never
+    #[arg(long, global = true, conflicts_with = "color")]
+    no_color: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the config path, size, and contents
+    Show,
+    /// Print only the config path (e.g. `cat $(pfm config path)`)
+    Path,
+    /// Check the config file for structural problems
+    Validate,
+    /// Print a global setting
+    ///
+    /// Keys: retry.attempts, retry.delay_ms, port_search_attempts, startup_wait_ms,
+    /// default_port_range, server_alive_interval, default_connect_timeout
+    Get {
+        key: String,
+    },
+    /// Change a global setting; new forwards use it unless they override it
+    ///
+    /// Examples:
+    ///   pfm config set server_alive_interval 30
+    ///   pfm config set default_port_range 20000-20999
+    ///   pfm config set default_connect_timeout none
+    Set {
+        key: String,
+        /// New value, or `none` to unset an optional setting
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommands {
+    /// List the snapshots in the backup directory, oldest first
+    List,
+}
+
+#[derive(Subcommand)]
+enum MonitorCommands {
+    /// Stop the background monitor
+    Stop,
+    /// Report whether the background monitor is running
+    Status,
+}
+
+#[derive(Subcommand)]
+enum ProfileCommands {
+    /// List profiles and how many forwards each holds
+    List,
+    /// Create an empty profile
+    New {
+        name: String,
+    },
+    /// Delete a profile; it must have no forwards
+    Delete {
+        name: String,
+    },
+}
+
+#[derive(Args)]
+struct AddArgs {
+    /// SSH host (user@hostname); omit it when using --env. `$VAR` and `${VAR}`
+    /// (quoted so the shell leaves them alone) are stored as written and expanded
+    /// from the environment each time the tunnel starts
+    #[arg(required_unless_present = "env")]
+    host: Option<String>,
+    /// Port mapping (local:remote, local:host:remote, or just local for same port)
+    #[arg(required_unless_present = "env")]
+    ports: Option<String>,
+    /// Read the SSH host from this environment variable instead of the HOST argument.
+    /// The resolved host is what gets saved.
+    #[arg(long, value_name = "NAME")]
+    env: Option<String>,
+    /// Host the ssh server forwards to, e.g. a database only it can reach [default: localhost]
+    #[arg(long, value_parser = parse_remote_host)]
+    remote_host: Option<String>,
+    /// Port of the ssh server (passed as `ssh -p`)
+    #[arg(long)]
+    ssh_port: Option<u16>,
+    /// Login user; takes precedence over a `user@` prefix in the host
+    #[arg(long)]
+    user: Option<String>,
+    /// Seconds ssh may spend connecting before giving up (`-o ConnectTimeout`)
+    #[arg(long)]
+    connect_timeout: Option<u64>,
+    /// Seconds to wait for the tunnel to accept connections
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+    /// Tag to group this forward under (repeatable)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Seconds to watch ssh for an early exit before assuming it is alive
+    /// (fractions allowed) [default: from config, 3]
+    #[arg(long, value_name = "SECS", value_parser = manager::parse_seconds)]
+    startup_timeout: Option<Duration>,
+    /// Times to retry if ssh exits immediately [default: from config]
+    #[arg(long)]
+    retry_attempts: Option<u8>,
+    /// Milliseconds to wait between retries [default: from config]
+    #[arg(long)]
+    retry_delay_ms: Option<u64>,
+    /// Retry this many times if ssh exits immediately, doubling the delay each
+    /// time (1s, 2s, 4s, ... by default), e.g. for a host that is still booting
+    #[arg(long, value_name = "N", conflicts_with = "retry_attempts")]
+    retries: Option<u8>,
+    /// Extra ssh option as KEY=VALUE, passed as `-o KEY=VALUE` (repeatable)
+    #[arg(short = 'o', long = "ssh-option", visible_alias = "ssh-opt",
+          value_name = "KEY=VALUE", value_parser = ssh::parse_ssh_option)]
+    ssh_options: Vec<String>,
+    /// Never prompt for a password or passphrase (`-o BatchMode=yes`); fail
+    /// straight away if key-based authentication doesn't work
+    #[arg(long)]
+    batch: bool,
+    /// Address the local port listens on, e.g. ::1 or 192.168.1.5 (`-L ADDR:PORT:...`)
+    /// [default: 127.0.0.1, which ssh binds on both IPv4 and IPv6 loopback]
+    #[arg(long, value_name = "ADDR", value_parser = parse_bind, conflicts_with_all = ["gateway", "local_only"])]
+    bind: Option<String>,
+    /// Bind the local port on all interfaces (`-o GatewayPorts=yes`) instead of loopback only.
+    /// Anyone who can reach this machine can then use the tunnel, so only enable
+    /// it on trusted networks.
+    #[arg(long, conflicts_with = "local_only")]
+    gateway: bool,
+    /// Bind the local port on loopback only (the default)
+    #[arg(long)]
+    local_only: bool,
+    /// Share one ssh connection with other multiplexed forwards to the same host
+    /// (`-o ControlMaster=auto`), saving a connection and an authentication per forward
+    #[arg(long)]
+    multiplex: bool,
+    /// Capture ssh's stderr in a log file, viewable with `pfm logs`
+    #[arg(long)]
+    log: bool,
+    /// Free-text description of the forward
+    #[arg(long)]
+    comment: Option<String>,
+    /// Fail if the local port is busy instead of picking the next free one
+    #[arg(long)]
+    no_remap: bool,
+    /// Ports to pick from if the local port is busy, e.g. 20000-20999
+    /// [default: settings.default_port_range in the config, else just above the busy port]
+    #[arg(long, value_name = "START-END", value_parser = port::parse_port_range,
+          conflicts_with = "no_remap")]
+    port_range: Option<port::PortRange>,
+    /// Allow a local port below 1024, which only root can bind on Linux
+    #[arg(long)]
+    allow_privileged: bool,
+    /// Do nothing if a forward with the same host and ports already exists
+    #[arg(long)]
+    if_not_exists: bool,
+    /// Add even if the host and remote port are already forwarded, without asking
+    #[arg(long)]
+    force: bool,
+    /// Id for the forward [default: generated from the host and ports]
+    #[arg(long, value_parser = parse_forward_id)]
+    id: Option<String>,
+    /// Stay attached like plain `ssh -N -L`: on Ctrl-C, stop the tunnel and remove the forward.
+    /// If ssh exits before then, it is reconnected
+    #[arg(long, visible_alias = "foreground")]
+    wait: bool,
+}
+
+/// A bind address as stored: an IP address without brackets, `localhost` or `*`.
+fn parse_bind(value: &str) -> Result<String, String> {
+    match value {
+        "localhost" | "*" => Ok(value.to_string()),
+        _ => port::parse_bind_addr(value).map(|addr| addr.to_string()),
+    }
+}
+
+fn parse_remote_host(value: &str) -> Result<String, String> {
+    manager::validate_remote_host(value)
+        .map(|()| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_forward_id(id: &str) -> Result<String, String> {
+    manager::validate_id(id).map_err(|e| e.to_string())?;
+    Ok(id.to_string())
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// File produced by `pfm export`, or a .env-style file with --format env-file
+    #[arg(required_unless_present = "from_ssh_config", conflicts_with = "from_ssh_config")]
+    file: Option<PathBuf>,
+    /// How FILE is written
+    #[arg(long, value_enum, default_value_t = ImportFormat::Json)]
+    format: ImportFormat,
+    /// Start tunnels for forwards read from an env file instead of only registering them
+    #[arg(long)]
+    start: bool,
+    /// Keep existing forwards and skip imported ones with the same id (default)
+    #[arg(long, conflicts_with = "replace")]
+    merge: bool,
+    /// Replace all existing forwards (running tunnels are not stopped)
+    #[arg(long)]
+    replace: bool,
+    /// Pick hosts from an ssh client config [default: ~/.ssh/config]
+    #[arg(long, value_name = "PATH", num_args = 0..=1, requires = "remote_port")]
+    from_ssh_config: Option<Option<PathBuf>>,
+    /// Remote (and local) port for hosts imported from an ssh config
+    #[arg(long)]
+    remote_port: Option<u16>,
+}
+
+#[derive(Args)]
+struct EditArgs {
+    /// Forward index or id
+    id: String,
+    /// New SSH host (user@hostname)
+    #[arg(long)]
+    host: Option<String>,
+    /// New local port
+    #[arg(long)]
+    local: Option<u16>,
+    /// New remote port
+    #[arg(long)]
+    remote: Option<u16>,
+    /// New ssh server port
+    #[arg(long)]
+    ssh_port: Option<u16>,
+    /// New free-text description (empty to clear)
+    #[arg(long)]
+    comment: Option<String>,
+    /// Capture ssh's stderr from the next time the tunnel starts
+    #[arg(long, conflicts_with = "no_log")]
+    log: bool,
+    /// Stop capturing ssh's stderr from the next time the tunnel starts
+    #[arg(long)]
+    no_log: bool,
+    /// Seconds to wait for a restarted tunnel to accept connections
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// How to print the forwards
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+    /// Shorthand for --format json
+    #[arg(long, conflicts_with_all = ["format", "porcelain"])]
+    json: bool,
+    /// Shorthand for --format porcelain
+    #[arg(long, conflicts_with = "format")]
+    porcelain: bool,
+    /// Print only counts, e.g. for a status bar [default format: {running}/{total}].
+    /// Placeholders: {running}, {stopped}, {total}
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1,
+          conflicts_with_all = ["format", "json", "porcelain"])]
+    summary: Option<Option<String>>,
+    /// Relaunch forwards whose recorded SSH process has died (see `pfm up` for stopped ones)
+    #[arg(long)]
+    restart_dead: bool,
+    /// Seconds to wait for each relaunched tunnel to accept connections
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+    /// Only show forwards with this tag (repeatable)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Only show forwards whose host, comment, or tags contain this text (case-insensitive)
+    #[arg(long)]
+    filter: Option<String>,
+    /// Only show forwards with this process status
+    #[arg(long, value_enum, default_value_t = StatusFilter::All)]
+    status: StatusFilter,
+    /// Shorthand for --status running
+    #[arg(long, conflicts_with_all = ["status", "stopped"])]
+    running: bool,
+    /// Shorthand for --status stopped
+    #[arg(long, conflicts_with = "status")]
+    stopped: bool,
+    /// Order in which forwards are printed (indices are unchanged)
+    #[arg(long, value_enum, default_value_t = SortKey::Index)]
+    sort_by: SortKey,
+    /// Print forwards under one heading per group instead of one section each
+    /// (table format only)
+    #[arg(long, value_enum, conflicts_with_all = ["json", "porcelain", "summary"])]
+    group_by: Option<GroupBy>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GroupBy {
+    /// One heading per SSH host, with a line per forward to it
+    Host,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    /// Every profile's forwards, without pids, for `pfm import`
+    Json,
+    /// One `Host` block with a `LocalForward` per forward of the active profile
+    SshConfig,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ImportFormat {
+    /// The output of `pfm export`
+    Json,
+    /// `PFM_FORWARD_<n>=<host> <ports>` lines; other variables are ignored
+    EnvFile,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable, colored listing
+    Table,
+    /// A JSON array of forwards
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Stable, tab-separated lines for scripts, one forward per line:
+    /// index, id, host, local port, remote port, pid (or -), running (true/false).
+    /// The format will not change between patch releases.
+    Porcelain,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    /// Index order: host, then local and remote port
+    Index,
+    Id,
+    Host,
+    LocalPort,
+    RemotePort,
+    /// Running forwards first
+    Status,
+    /// Oldest first; forwards without a creation time sort last
+    Created,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum StatusFilter {
+    Running,
+    Stopped,
+    All,
+}
+
+impl ListArgs {
+    fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else if self.porcelain {
+            OutputFormat::Porcelain
+        } else {
+            self.format
+        }
+    }
+
+    fn status_filter(&self) -> StatusFilter {
+        if self.running {
+            StatusFilter::Running
+        } else if self.stopped {
+            StatusFilter::Stopped
+        } else {
+            self.status
+        }
+    }
+
+    fn matches(&self, forward: &PortForward, running: bool) -> bool {
+        let status_matches = match self.status_filter() {
+            StatusFilter::All => true,
+            StatusFilter::Running => running,
+            StatusFilter::Stopped => !running,
+        };
+
+        status_matches
+            && forward.matches_tags(&self.tags)
+            && self.filter.as_deref().is_none_or(|pattern| forward.matches_filter(pattern))
+    }
+
+    fn is_filtered(&self) -> bool {
+        !self.tags.is_empty() || self.filter.is_some() || self.status_filter() != StatusFilter::All
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Add a new SSH port forward
@@ -27,59 +438,773 @@ enum Commands {
     /// Examples:
     ///   pfm add user@server.com 8080:80
     ///   pfm add server.com 3000
-    Add {
-        /// SSH host (user@hostname)
-        host: String,
-        /// Port mapping (local:remote or just local for same port)
-        ports: String
+    ///   pfm add bastion 8080:db.internal:5432
+    ///   pfm add --env SSH_HOST 5432
+    Add(Box<AddArgs>),
+    /// Add every forward listed in a file
+    ///
+    /// Each line is what would follow `pfm add`: a host, ports, and any options.
+    /// Blank lines and `#` comments are skipped. A line that fails is reported
+    /// and the rest are still added.
+    ///
+    /// Examples:
+    ///   pfm add-file forwards.txt
+    AddFile {
+        /// File with one forward per line, e.g. `db.internal 5432 --tag db`
+        file: PathBuf,
     },
     /// List all configured port forwards
-    List,
+    List(ListArgs),
+    /// Show full details for a single forward
+    Show {
+        /// Index of the forward (as shown by `pfm list`)
+        index: usize,
+    },
     /// Delete port forward(s)
     /// 
     /// Examples:
     ///   pfm delete 0 1 2    # Delete forwards at index 0, 1, 2
     ///   pfm delete all      # Delete all forwards
+    ///   pfm delete --tag projectX
     Delete {
         /// Forward indices or 'all'
+        #[arg(required_unless_present = "tags")]
+        ids: Vec<String>,
+        /// Delete every forward with this tag (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Show the ssh output captured for a forward
+    ///
+    /// Examples:
+    ///   pfm logs 0
+    ///   pfm log 0 -n 100 --follow
+    #[command(visible_alias = "log")]
+    Logs {
+        /// Forward index or id
+        id: String,
+        /// Number of trailing lines to print
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+        /// Keep printing new output as it is written
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Update fields of an existing forward
+    ///
+    /// Changing the host or ports regenerates the forward's id, so its
+    /// index in `pfm list` may change. A running tunnel is restarted.
+    ///
+    /// Examples:
+    ///   pfm edit 0 --remote 5433
+    ///   pfm edit 0 --comment "staging database"
+    ///   pfm edit 0 --comment ""    # Clear the comment
+    Edit(EditArgs),
+    /// Clone a forward onto a new local port
+    ///
+    /// The clone gets a fresh id and its own ssh process.
+    ///
+    /// Examples:
+    ///   pfm duplicate 0                    # Next available local port
+    ///   pfm clone db_5432_5432 --local 9090
+    #[command(visible_alias = "clone")]
+    Duplicate {
+        /// Forward index or id
+        id: String,
+        /// Local port for the clone (defaults to the next available port)
+        #[arg(long, visible_alias = "local")]
+        local_port: Option<u16>,
+        /// Seconds to wait for the tunnel to accept connections
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Probe forwards end-to-end by connecting through the local port
+    ///
+    /// Examples:
+    ///   pfm health                   # Check every forward
+    ///   pfm health 0 2 --http-path /healthz
+    Health {
+        /// Forward indices or ids (defaults to all)
         ids: Vec<String>,
+        /// Also issue an HTTP GET for this path and require a 2xx/3xx response
+        #[arg(long)]
+        http_path: Option<String>,
+        /// Seconds to wait for each probe
+        #[arg(long, default_value_t = 3)]
+        timeout: u64,
+    },
+    /// Measure how long connecting through a running forward takes
+    ///
+    /// Examples:
+    ///   pfm ping 0                   # 5 connects, then min/avg/max
+    ///   pfm ping db -c 20
+    Ping {
+        /// Forward index or id
+        id: String,
+        /// Number of connects to time
+        #[arg(short = 'c', long, default_value_t = 5,
+              value_parser = clap::value_parser!(u8).range(1..))]
+        count: u8,
+        /// Seconds to wait for each connect
+        #[arg(long, default_value_t = 3)]
+        timeout: u64,
+    },
+    /// Remove forwards created longer ago than a given age
+    ///
+    /// Examples:
+    ///   pfm prune --older-than 7d
+    ///   pfm prune --older-than 12h --stopped-only
+    Prune {
+        /// Minimum age, e.g. 30m, 2h, 7d
+        #[arg(long, value_parser = manager::parse_duration)]
+        older_than: Duration,
+        /// Only prune forwards whose process is not running
+        #[arg(long)]
+        stopped_only: bool,
+    },
+    /// Open a forwarded port in the default browser
+    ///
+    /// Examples:
+    ///   pfm open 0
+    ///   pfm open 0 /admin
+    Open {
+        /// Index of the forward
+        index: usize,
+        /// Path appended to the URL
+        path: Option<String>,
+    },
+    /// Change a forward's id
+    ///
+    /// The running tunnel, if any, is unaffected.
+    ///
+    /// Examples:
+    ///   pfm rename 0 staging-db
+    ///   pfm rename db_5432_5432 prod-db
+    Rename {
+        /// Forward index or current id
+        id: String,
+        /// New id; must not be a number or already in use in the profile
+        #[arg(value_parser = parse_forward_id)]
+        new_id: String,
+    },
+    /// Move a forward into another profile
+    ///
+    /// The profile is created if needed. A running tunnel is not restarted;
+    /// only its config entry moves.
+    ///
+    /// Examples:
+    ///   pfm move 0 --to-profile work
+    Move {
+        /// Index of the forward in the active profile
+        index: usize,
+        /// Profile to move the forward into
+        #[arg(long)]
+        to_profile: String,
+    },
+    /// Include a forward in bulk operations again
+    Enable {
+        /// Forward index or id
+        id: String,
+    },
+    /// Keep a forward defined but skip it in bulk operations (restart, watch)
+    Disable {
+        /// Forward index or id
+        id: String,
+    },
+    /// Start every enabled forward that is not running
+    ///
+    /// Unlike `pfm list --restart-dead`, this also starts forwards that were
+    /// registered, imported, or stopped and so have no recorded pid.
+    ///
+    /// Examples:
+    ///   pfm up
+    ///   pfm up --timeout 30
+    Up {
+        /// Seconds to wait for each tunnel to accept connections
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Print the `pfm add` command that would recreate a forward
+    ///
+    /// Examples:
+    ///   pfm show-cmd 0
+    ///   pfm show-cmd db_5432_5432
+    ShowCmd {
+        /// Forward index or id
+        id: String,
+    },
+    /// Open an interactive ssh session to a forward's host
+    ///
+    /// Uses the forward's ssh port and options, so no lookup is needed.
+    ///
+    /// Examples:
+    ///   pfm connect 0
+    ///   pfm connect db_5432_5432
+    Connect {
+        /// Forward index or id
+        id: String,
+    },
+    /// Show which forward, if any, owns a local port
+    ///
+    /// Every profile is searched, since they all share the machine's ports.
+    ///
+    /// Examples:
+    ///   pfm which 5432
+    Which {
+        /// Local port to look up
+        port: u16,
+    },
+    /// Interactive view of the forwards with live status
+    ///
+    /// Select a forward with the arrow keys (or k/j), then press s to stop it
+    /// or r to restart it; q quits. Built with the `tui` feature.
+    #[cfg(feature = "tui")]
+    Ui {
+        /// Seconds to wait for a restarted tunnel to accept connections
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Print a free local port, for use in scripts
+    ///
+    /// Searches `--range`, or `port_search_attempts` ports from `--start`. With
+    /// neither, the `default_port_range` setting is used if set, else ports from 1024.
+    ///
+    /// Examples:
+    ///   LOCAL=$(pfm find-port --start 8000)
+    ///   pfm find-port --range 20000-20999
+    FindPort {
+        /// First port to try
+        #[arg(long, conflicts_with = "range")]
+        start: Option<u16>,
+        /// Ports to search, as START-END
+        #[arg(long, value_name = "START-END", value_parser = port::parse_port_range)]
+        range: Option<port::PortRange>,
+    },
+    /// Check whether a local port is free, e.g. before `pfm add`
+    ///
+    /// Exits with 0 if the port is free and 1 if it is taken, naming the
+    /// forward that holds it when pfm manages it.
+    ///
+    /// Examples:
+    ///   pfm check-port 8080 && pfm add server 8080:80
+    CheckPort {
+        /// Local port to check
+        port: u16,
     },
     /// Remove forwards whose SSH processes have died
-    Cleanup,
+    ///
+    /// A pid that was reused by an unrelated process counts as dead and is never killed.
+    Cleanup {
+        /// Show what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Also kill stray ssh processes that run a configured forward's tunnel
+        /// under an unrecorded pid (e.g. left behind by a crash). pfm cannot tell
+        /// these from tunnels started by a generated systemd/launchd unit or by
+        /// hand, so they are listed and you are asked before anything is killed
+        #[arg(long)]
+        reap_orphans: bool,
+        /// Kill the orphans found by --reap-orphans without asking
+        #[arg(long, requires = "reap_orphans")]
+        force: bool,
+    },
+    /// Remove forwards that have no pid and whose local port is free
+    ///
+    /// Examples:
+    ///   pfm gc
+    ///   pfm gc --all-stopped --yes
+    Gc {
+        /// Remove every forward without a pid, even if its local port is in use
+        #[arg(long)]
+        all_stopped: bool,
+        /// Remove without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Poll forwards and report when their SSH processes die
+    ///
+    /// Examples:
+    ///   pfm watch
+    ///   pfm watch --notify
+    ///   pfm watch --follow --interval 2
+    ///   pfm watch --notify --webhook-url https://hooks.example.com/pfm
+    Watch {
+        /// Seconds between checks
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        /// Send a notification when a tunnel dies (desktop, unless --webhook-url is given;
+        /// desktop notifications need pfm built with the `notify` feature)
+        #[arg(long)]
+        notify: bool,
+        /// POST a JSON payload to this URL instead of showing a desktop notification
+        /// (sent with `curl`, which must be on $PATH)
+        #[arg(long, requires = "notify")]
+        webhook_url: Option<String>,
+        /// Relaunch dead tunnels, backing off exponentially (1s, 2s, 4s, ... up to 5m)
+        /// while they keep failing
+        #[arg(long)]
+        follow: bool,
+        /// Seconds to wait for a relaunched tunnel to accept connections
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+        /// Record when each tunnel last had a client connected, so `list` can show
+        /// how long it has been idle (Linux only)
+        #[arg(long)]
+        track_activity: bool,
+        /// Print one JSON object per event (started, died, reconnecting,
+        /// reconnected, reconnect_failed) instead of human-readable text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Relaunch dead tunnels from a background process (like `watch --follow`)
+    ///
+    /// The daemon's pid is kept in monitor.pid and its output in monitor.log,
+    /// both next to the config file.
+    ///
+    /// Examples:
+    ///   pfm monitor --interval 30
+    ///   pfm monitor status
+    ///   pfm monitor stop
+    Monitor {
+        #[command(subcommand)]
+        command: Option<MonitorCommands>,
+        /// Seconds between checks
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+        /// Seconds to wait for a relaunched tunnel to accept connections
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+        /// Run the monitor loop in this process instead of detaching
+        #[arg(long, hide = true)]
+        foreground: bool,
+    },
+    /// Snapshot the config file before risky changes
+    ///
+    /// Snapshots go to the backups directory next to the config file unless
+    /// --output is given.
+    ///
+    /// Examples:
+    ///   pfm backup
+    ///   pfm backup --output ~/pfm-config.json
+    ///   pfm backup list
+    Backup {
+        #[command(subcommand)]
+        command: Option<BackupCommands>,
+        /// Write the snapshot to this file instead of the backups directory
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Replace the config file with a snapshot from `pfm backup`
+    ///
+    /// The current config is backed up first, so a restore can itself be undone.
+    ///
+    /// Examples:
+    ///   pfm restore ~/.config/pfm/backups/1760000000_config.json
+    ///   pfm restore snapshot.json --dry-run
+    Restore {
+        /// Snapshot file to restore
+        source: PathBuf,
+        /// Check the snapshot and show what it holds without restoring it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Diagnose common configuration problems
+    Doctor,
+    /// Print the version, git commit, and build target
+    Version,
+    /// Write all forwards (without pids) as JSON for use on another machine
+    ///
+    /// Examples:
+    ///   pfm export > forwards.json
+    ///   pfm export --file forwards.json
+    ///   pfm export --format ssh-config >> ~/.ssh/config
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// What to write
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// With ssh-config, name each Host after the forward's --id when it was
+        /// chosen explicitly, instead of pfm-<id>
+        #[arg(long)]
+        include_labels: bool,
+    },
+    /// Load forwards written by `pfm export`, or hosts from an ssh config
+    ///
+    /// Imported forwards are stopped; start them with `pfm up`.
+    ///
+    /// Examples:
+    ///   pfm import forwards.json             # Merge, skipping existing ids
+    ///   pfm import forwards.json --replace   # Discard current forwards first
+    ///   pfm import --from-ssh-config --remote-port 5432
+    ///   pfm import --format env-file .pfm.env --start
+    Import(ImportArgs),
+    /// Print a systemd service unit that keeps a forward running
+    ///
+    /// Examples:
+    ///   pfm systemd-unit 0
+    ///   pfm systemd-unit 0 --output /etc/systemd/system/pfm-db.service
+    SystemdUnit {
+        /// Index of the forward
+        index: usize,
+        /// Write the unit to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Print a launchd plist that keeps a forward running (macOS)
+    ///
+    /// Examples:
+    ///   pfm launchd-plist 0
+    ///   pfm launchd-plist 0 --output ~/Library/LaunchAgents/com.pfm.db_5432_5432.plist
+    LaunchdPlist {
+        /// Index of the forward
+        index: usize,
+        /// Write the plist to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Inspect the config file
+    #[command(subcommand)]
+    Config(ConfigCommands),
+    /// Manage profiles that keep separate sets of forwards
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
     /// Generate shell completions
     Completions {
-        /// Shell to generate completions for
+        /// Shell to generate completions for (bash and fish also complete forward ids)
         #[arg(value_enum)]
         shell: Shell,
+    },
+    /// List forward ids or indices for shell completion scripts
+    #[command(name = "list-completions", hide = true)]
+    Complete {
+        #[arg(value_enum)]
+        kind: CompleteKind,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CompleteKind {
+    Ids,
+    Indices,
+}
+
+
+/// Some of the requested items failed or were not found.
+const EXIT_PARTIAL: u8 = 2;
+/// The command had nothing to act on.
+const EXIT_NOTHING_TO_DO: u8 = 3;
+
+/// An outcome with its own exit code, returned through `anyhow` so commands
+/// can stop early the same way they fail.
+#[derive(Debug)]
+struct Exit {
+    code: u8,
+    /// Printed as the error; `None` for outcomes that were already reported
+    message: Option<String>,
+}
+
+impl Exit {
+    fn partial(message: impl Into<String>) -> anyhow::Error {
+        Exit { code: EXIT_PARTIAL, message: Some(message.into()) }.into()
+    }
+
+    fn nothing_to_do() -> anyhow::Error {
+        Exit { code: EXIT_NOTHING_TO_DO, message: None }.into()
+    }
+
+    /// A plain failure whose details the command has already printed.
+    fn reported_failure() -> anyhow::Error {
+        Exit { code: 1, message: None }.into()
+    }
+}
+
+impl std::fmt::Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message.as_deref().unwrap_or("nothing to do"))
     }
 }
 
+impl std::error::Error for Exit {}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => match e.downcast_ref::<Exit>() {
+            Some(exit) => {
+                if let Some(message) = &exit.message {
+                    eprintln!("Error: {}", message);
+                }
+                ExitCode::from(exit.code)
+            }
+            None => {
+                eprintln!("Error: {:?}", e);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn run() -> Result<()> {
+    // clap exits with 2 on bad arguments, which would read as a partial failure.
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        std::process::exit(if e.use_stderr() { 1 } else { 0 });
+    });
+    // JSON watch events own stdout, and so do machine-readable listings and the
+    // full-screen UI; keep progress messages out of all of them.
+    let owns_stdout = match &cli.command {
+        Commands::Watch { json, .. } => *json,
+        Commands::List(args) => !matches!(args.output_format(), OutputFormat::Table) || args.summary.is_some(),
+        _ => false,
+    };
+    #[cfg(feature = "tui")]
+    let owns_stdout = owns_stdout || matches!(cli.command, Commands::Ui { .. });
+    let out = &OutputConfig { quiet: cli.quiet || owns_stdout };
+    apply_color_choice(if cli.no_color { ColorChoice::Never } else { cli.color });
 
     match &cli.command {
         Commands::Completions { shell } => {
-            generate_completions(*shell);
+            completions::generate_completions(*shell, &mut Cli::command());
+        }
+        Commands::Doctor => {
+            doctor::run()?;
+        }
+        Commands::Version => {
+            print_version();
+        }
+        Commands::Config(command) => {
+            run_config_command(command, out)?;
+        }
+        Commands::Backup { command: Some(BackupCommands::List), .. } => {
+            list_backups(out)?;
+        }
+        Commands::Backup { command: None, output } => {
+            let path = Config::backup(output.as_deref())?;
+            info!(out, "{} {}", "✓ Backed up config to".green(), path.display());
+        }
+        Commands::Restore { source, dry_run } => {
+            restore_config(source, *dry_run, out)?;
         }
         _ => {
             // Load config for all other commands
             let mut config = Config::load()?;
+            config.use_profile(&cli.profile);
+
+            let manages_profiles = matches!(cli.command, Commands::Profile { .. });
+            if !manages_profiles && cli.profile != DEFAULT_PROFILE && !config.has_profile(&cli.profile) {
+                anyhow::bail!(
+                    "Profile '{}' does not exist. Create it with: pfm profile new {}",
+                    cli.profile, cli.profile
+                );
+            }
             
             match &cli.command {
-                Commands::Add { host, ports } => {
-                    add_forward(&mut config, host, ports)?;
+                Commands::Add(args) => {
+                    add_forward(&mut config, args, out)?;
+                }
+                Commands::AddFile { file } => {
+                    add_from_file(&mut config, file, out)?;
+                }
+                Commands::List(args) => {
+                    let failed = if args.restart_dead {
+                        restart_dead_forwards(&mut config, Duration::from_secs(args.timeout), out)?
+                    } else {
+                        0
+                    };
+                    list_forwards(&mut config, args, out)?;
+                    if failed > 0 {
+                        return Err(Exit::partial(format!("{} forward(s) could not be revived", failed)));
+                    }
+                }
+                Commands::Show { index } => {
+                    show_forward(&config, *index)?;
                 }
-                Commands::List => {
-                    list_forwards(&config);
+                Commands::Delete { ids, tags } => {
+                    delete_forwards(&mut config, ids, tags, out)?;
                 }
-                Commands::Delete { ids } => {
-                    delete_forwards(&mut config, ids)?;
+                Commands::Logs { id, lines, follow } => {
+                    show_logs(&config, id, *lines, *follow, out)?;
                 }
-                Commands::Cleanup => {
-                    cleanup_dead_forwards(&mut config)?;
+                Commands::Edit(args) => {
+                    edit_forward(&mut config, args, out)?;
                 }
-                Commands::Completions { .. } => unreachable!(),
+                Commands::Duplicate { id, local_port, timeout } => {
+                    duplicate_forward(&mut config, id, *local_port, Duration::from_secs(*timeout), out)?;
+                }
+                Commands::Health { ids, http_path, timeout } => {
+                    check_health(&config, ids, http_path.as_deref(), Duration::from_secs(*timeout))?;
+                }
+                Commands::Ping { id, count, timeout } => {
+                    ping_forward(&config, id, *count, Duration::from_secs(*timeout))?;
+                }
+                Commands::Prune { older_than, stopped_only } => {
+                    let ids = manager::forwards_older_than(&config, *older_than, *stopped_only);
+                    if ids.is_empty() {
+                        info!(out, "{}", "No forwards to prune".dimmed());
+                        return Err(Exit::nothing_to_do());
+                    }
+                    delete_forwards(&mut config, &ids, &[], out)?;
+                }
+                Commands::Open { index, path } => {
+                    open_forward(&config, *index, path.as_deref(), out)?;
+                }
+                Commands::Connect { id } => {
+                    connect_shell(&config, id, out)?;
+                }
+                Commands::Rename { id, new_id } => {
+                    rename_forward(&mut config, id, new_id, out)?;
+                }
+                Commands::Move { index, to_profile } => {
+                    move_forward(&mut config, *index, to_profile, out)?;
+                }
+                Commands::Enable { id } => {
+                    set_forward_enabled(&mut config, id, true, out)?;
+                }
+                Commands::Disable { id } => {
+                    set_forward_enabled(&mut config, id, false, out)?;
+                }
+                Commands::Up { timeout } => {
+                    let failed = start_stopped_forwards(&mut config, Duration::from_secs(*timeout), out)?;
+                    if failed > 0 {
+                        return Err(Exit::partial(format!("{} forward(s) could not be started", failed)));
+                    }
+                }
+                Commands::ShowCmd { id } => {
+                    let forward = config.resolve_forward(id)
+                        .with_context(|| format!("Forward not found: {}", id))?;
+                    println!("{}", recreate_command(config.active_profile(), forward));
+                }
+                Commands::Which { port } => {
+                    which_port(&config, *port);
+                }
+                Commands::CheckPort { port } => {
+                    check_port(&config, *port)?;
+                }
+                Commands::FindPort { start, range } => {
+                    let port = match (start, range.or(config.settings.default_port_range)) {
+                        (None, Some(range)) => range.find_available(DEFAULT_BIND_ADDR, &config.configured_ports())?,
+                        (start, _) => port::find_available_port(
+                            DEFAULT_BIND_ADDR,
+                            start.unwrap_or(port::FIRST_UNPRIVILEGED_PORT),
+                            config.settings.port_search_attempts,
+                            &config.configured_ports(),
+                        )?,
+                    };
+                    println!("{}", port);
+                }
+                #[cfg(feature = "tui")]
+                Commands::Ui { timeout } => {
+                    tui::run(config.active_profile(), Duration::from_secs(*timeout), out)?;
+                }
+                Commands::Gc { all_stopped, yes } => {
+                    collect_inactive_forwards(&mut config, *all_stopped, *yes, out)?;
+                }
+                Commands::Cleanup { dry_run, reap_orphans, force } => {
+                    let cleaned = cleanup_dead_forwards(&mut config, *dry_run, out)?;
+                    let reaped = *reap_orphans && reap_orphans_of(&config, *dry_run, *force, out)?;
+                    if !cleaned && !reaped {
+                        return Err(Exit::nothing_to_do());
+                    }
+                }
+                Commands::Watch { interval, notify, webhook_url, follow, timeout, track_activity, json } => {
+                    if *notify && webhook_url.is_none() && !Notifier::DESKTOP_AVAILABLE {
+                        anyhow::bail!(
+                            "Desktop notifications need pfm built with the `notify` feature; pass --webhook-url instead"
+                        );
+                    }
+                    let options = WatchOptions {
+                        interval: Duration::from_secs(*interval),
+                        notifier: notify.then(|| match webhook_url {
+                            Some(url) => Notifier::Webhook(url.clone()),
+                            None => Notifier::Desktop,
+                        }),
+                        follow: *follow,
+                        ready_timeout: Duration::from_secs(*timeout),
+                        track_activity: *track_activity,
+                        json: *json,
+                    };
+                    watch_forwards(&cli.profile, &options, out)?;
+                }
+                Commands::Monitor { command, interval, timeout, foreground } => {
+                    let (interval, timeout) = (Duration::from_secs(*interval), Duration::from_secs(*timeout));
+                    match command {
+                        Some(MonitorCommands::Stop) => stop_monitor(out)?,
+                        Some(MonitorCommands::Status) => monitor_status()?,
+                        None if *foreground => {
+                            let options = WatchOptions {
+                                interval,
+                                notifier: None,
+                                follow: true,
+                                ready_timeout: timeout,
+                                track_activity: false,
+                                json: false,
+                            };
+                            watch_forwards(&cli.profile, &options, out)?;
+                        }
+                        None => start_monitor(&cli.profile, interval, timeout, out)?,
+                    }
+                }
+                Commands::Export { file, format, include_labels } => {
+                    export_forwards(&config, file.as_deref(), *format, *include_labels, out)?;
+                }
+                Commands::Import(args) => {
+                    match (&args.from_ssh_config, &args.file) {
+                        (Some(path), _) => {
+                            let remote_port = args.remote_port.context("--remote-port is required")?;
+                            import_from_ssh_config(&mut config, path.as_deref(), remote_port, out)?;
+                        }
+                        (None, Some(file)) if args.format == ImportFormat::EnvFile => {
+                            if args.replace {
+                                anyhow::bail!("--replace is not supported with --format env-file");
+                            }
+                            import_env_file(&mut config, file, args.start, out)?;
+                        }
+                        (None, Some(file)) => {
+                            if args.start {
+                                anyhow::bail!("--start only applies to --format env-file");
+                            }
+                            let mode = if args.replace { ImportMode::Replace } else { ImportMode::Merge };
+                            import_forwards(&mut config, file, mode, out)?;
+                        }
+                        (None, None) => unreachable!("clap requires a file or --from-ssh-config"),
+                    }
+                }
+                Commands::SystemdUnit { index, output } => {
+                    let forward = config.get_forward_by_index(*index)
+                        .with_context(|| format!("Invalid index: {}", index))?;
+                    let ssh_path = ssh::find_ssh_binary().context("ssh not found in $PATH")?;
+                    let user = std::env::var("USER").context("$USER is not set")?;
+                    let unit = pfm::systemd::render_unit(forward, &ssh_path, &user);
+                    write_output(&unit, output.as_deref(), out)?;
+                }
+                Commands::LaunchdPlist { index, output } => {
+                    let forward = config.get_forward_by_index(*index)
+                        .with_context(|| format!("Invalid index: {}", index))?;
+                    let ssh_path = ssh::find_ssh_binary().context("ssh not found in $PATH")?;
+                    let plist = pfm::launchd::render_plist(forward, &ssh_path);
+                    write_output(&plist, output.as_deref(), out)?;
+                }
+                Commands::Profile { command } => {
+                    manage_profiles(&mut config, command, out)?;
+                }
+                Commands::Complete { kind } => {
+                    for (index, forward) in config.get_sorted_forwards().into_iter().enumerate() {
+                        match kind {
+                            CompleteKind::Ids => println!("{}", forward.id),
+                            CompleteKind::Indices => println!("{}", index),
+                        }
+                    }
+                }
+                Commands::Completions { .. }
+                | Commands::Doctor
+                | Commands::Version
+                | Commands::Config(_)
+                | Commands::Backup { .. }
+                | Commands::Restore { .. } => unreachable!(),
             }
         }
     }
@@ -87,234 +1212,1957 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn generate_completions(shell: Shell) {
-    let mut cmd = Cli::command();
-    generate(
-        shell,
-        &mut cmd,
-        "pfm",
-        &mut io::stdout()
-    );
-}
-fn parse_ports(ports: &str) -> Result<(u16, u16)> {
-    if ports.contains(':') {
-        let parts: Vec<&str> = ports.split(':').collect();
-        if parts.len() != 2 {
-            anyhow::bail!("Invalid format '{}'. Use LOCAL:REMOTE or just PORT", ports);
+fn apply_color_choice(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                && io::stdout().is_terminal()
         }
-
-        let local = parts[0].parse::<u16>()
-            .context("Invalid local port")?;
-        let remote = parts[1].parse::<u16>()
-            .context("Invalid remote port")?;
-        Ok((local, remote))
-    } else {
-        let port = ports.parse::<u16>()
-            .context("Invalid port number")?;
-        Ok((port, port))
-    }
+    };
+    colored::control::set_override(enabled);
 }
 
-fn add_forward(config: &mut Config, host: &str, ports: &str) -> Result<()> {
-    let (mut local, remote) = parse_ports(ports)?;
-
-    let original_port = local;
-    if !port::is_port_available(local) {
-        println!("{}", format!("Port {} is already in use", local).yellow());
+fn print_version() {
+    println!("pfm {}", env!("CARGO_PKG_VERSION"));
+    println!("commit: {}", option_env!("PFM_GIT_HASH").unwrap_or("unknown"));
+    println!("target: {}", env!("PFM_TARGET"));
+}
 
-        if let Some(new_port) = port::find_available_port(local+1) {
-            local = new_port;
-            println!("{}", format!("Using port {} instead", local).green());
-        } else {
-            anyhow::bail!("No available ports found!");
+fn run_config_command(command: &ConfigCommands, out: &OutputConfig) -> Result<()> {
+    let config_path = Config::config_path()?;
+    match command {
+        ConfigCommands::Path => {
+            println!("{}", config_path.display());
         }
-    }
-    let tunnel = SshTunnel::start(host, local, remote)?;
-    let pid = tunnel.pid();
-
-    std::mem::forget(tunnel);
-
-    let id  = format!("{}_{}_{}",
-        host.replace("@", "_at_"),
-        local,
-        remote);
-    let forward = PortForward {
-        id: id.clone(),
-        host: host.to_string(),
-        local_port: local,
-        remote_port: remote,
-        pid: Some(pid)
-    };
-    config.add_forward(forward);
-    config.save()?;
+        ConfigCommands::Validate => {
+            // Parse directly: `Config::load` would set a corrupt file aside
+            // instead of reporting it.
+            let config = match std::fs::read_to_string(&config_path) {
+                Ok(contents) => Config::from_json(&contents)
+                    .with_context(|| format!("{} is not valid", config_path.display()))?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Config::new(),
+                Err(e) => return Err(e).context("Failed to read config file"),
+            };
+            let problems = config.validate();
+            for problem in &problems {
+                println!("{} {}", "✗".red(), problem);
+            }
+            if !problems.is_empty() {
+                anyhow::bail!("{} problem(s) found in {}", problems.len(), config_path.display());
+            }
+            info!(out, "{} {}", "✓ Config is valid:".green(), config_path.display());
+        }
+        ConfigCommands::Get { key } => {
+            println!("{}", Config::load()?.settings.get(key)?);
+        }
+        ConfigCommands::Set { key, value } => {
+            let mut config = Config::load()?;
+            config.settings.set(key, value)?;
+            config.save()?;
+            info!(out, "{} {} = {}", "✓ Set".green(), key, config.settings.get(key)?);
+        }
+        ConfigCommands::Show => {
+            println!("{}: {}", "Path".cyan(), config_path.display());
+            if !config_path.exists() {
+                println!("{}", "No config file yet; it is created by the first change.".dimmed());
+                return Ok(());
+            }
 
-    println!("\n{}", "✓ Port forward created!".green().bold());
-    println!("{}", format!("  ID: {}", id).cyan());
-    println!("  {}:{} → {}:{}", 
-             "localhost".dimmed(), 
-             local.to_string().cyan(), 
-             host.cyan(), 
-             remote.to_string().cyan());
-    println!("  {}: {}", "PID".cyan(), pid);
+            let contents = std::fs::read_to_string(&config_path)
+                .context("Failed to read config file")?;
+            println!("{}: {} bytes\n", "Size".cyan(), contents.len());
 
-    if original_port != local {
-            println!("{}", format!("\n⚠ Port remapped from {} to {}", original_port, local).yellow());
+            match serde_json::from_str::<serde_json::Value>(&contents) {
+                Ok(value) => println!("{}", serde_json::to_string_pretty(&value)?),
+                Err(e) => {
+                    println!("{}", contents);
+                    eprintln!("{}", format!("⚠ Config is not valid JSON: {}", e).yellow());
+                }
+            }
         }
+    }
     Ok(())
 }
 
-
-
-fn list_forwards(config: &Config) {
-    if config.forwards.is_empty() {
-        println!("{}", "No port forwards configured.".yellow());
-        println!("\n{}", "Add one with: pfm add <host> <ports>".dimmed());
-        return;
+fn list_backups(out: &OutputConfig) -> Result<()> {
+    let backups = Config::list_backups()?;
+    if backups.is_empty() {
+        info!(out, "{}", format!("No backups in {}", Config::backup_dir()?.display()).dimmed());
+        return Ok(());
     }
+    for path in backups {
+        let age = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map(|elapsed| format_age(elapsed.as_secs()))
+            .unwrap_or_default();
+        println!("{}  {}", path.display(), age.dimmed());
+    }
+    Ok(())
+}
 
-    let total = config.forwards.len();
-    let running = config.forwards.values()
-        .filter(|f| f.pid.map(port::is_process_running).unwrap_or(false))
-        .count();
+/// Print the profiles and forward counts held by `config`.
+fn print_profile_summary(config: &Config) {
+    let mut profiles: Vec<(&String, usize)> = config.profiles.iter()
+        .map(|(name, forwards)| (name, forwards.len()))
+        .collect();
+    profiles.sort();
+    for (name, count) in profiles {
+        println!("  {}: {} forward(s)", name.cyan(), count);
+    }
+}
 
-    println!("\n{} ({} running, {} total)\n", 
-             "Port forwards:".bold().underline(),
-             running.to_string().green(),
-             total);
-
-    for (index, forward) in config.get_sorted_forwards().iter().enumerate() {
-        println!("  {}: {}", "ID".cyan(), index.to_string().bold());
-        println!("  {}:  {}", "Host".cyan(), forward.host);
-        println!("  {}: {} → {}", 
-                 "Ports".cyan(), 
-                 forward.local_port, 
-                 forward.remote_port);
+fn restore_config(source: &std::path::Path, dry_run: bool, out: &OutputConfig) -> Result<()> {
+    let config = Config::read_backup(source)?;
+    if dry_run {
+        println!("{}{} is a valid config:", DRY_RUN_PREFIX, source.display());
+        print_profile_summary(&config);
+        return Ok(());
+    }
 
-        if let Some(pid) = forward.pid {
-            let status = if port::is_process_running(pid) {
-                "● Running".green()
-            } else {
-                "○ Stopped".yellow()
-            };
-            println!("  {}:   {} ({})", "PID".cyan(), pid, status);
-        }
-        
-        println!();
+    if Config::config_path()?.exists() {
+        let previous = Config::backup(None)?;
+        info!(out, "{}", format!("Backed up the current config to {}", previous.display()).dimmed());
     }
+    Config::restore(source)?;
+    info!(out, "{} {}", "✓ Restored config from".green(), source.display());
+    print_profile_summary(&config);
+    Ok(())
 }
 
-fn delete_forwards(config: &mut Config, ids: &[String]) -> Result<()> {
-    let mut deleted_count = 0;
-    let mut errors = Vec::new();
-    
-    // Check for "all" keyword
-    let ids_to_delete: Vec<String> = if ids.len() == 1 && ids[0] == "all" {
-        println!("{}", format!("Deleting all {} forward(s)...\n", config.forwards.len()).yellow());
-        config.forwards.keys().cloned().collect()
-    } else {
-        // Resolve indices to IDs
-        let mut result = Vec::new();
-        for id_str in ids {
-            if let Ok(index) = id_str.parse::<usize>() {
-                if let Some(forward) = config.get_forward_by_index(index) {
-                    result.push(forward.id.clone());
-                } else {
-                    let error = format!("✗ Invalid index: {}", index);
-                    eprintln!("{}", error.red());
-                    errors.push(error);
-                }
-            } else {
-                result.push(id_str.to_string());
-            }
-        }
-        result
+/// The host and port mapping for `add`. With `--env` the host comes from the
+/// environment, so the one positional argument is the port mapping.
+fn resolve_host_and_ports(args: &AddArgs) -> Result<(String, String)> {
+    let Some(name) = &args.env else {
+        let host = args.host.clone().context("HOST is required")?;
+        let ports = args.ports.clone().context("PORTS is required")?;
+        return Ok((host, ports));
     };
-    
-    // Delete all collected IDs
-    for id in ids_to_delete {
-        if let Some(forward) = config.remove_forward(&id) {
-            if let Some(pid) = forward.pid {
-                if let Err(e) = kill_process(pid) {
-                    eprintln!("{}", format!("  ⚠ Warning: {}", e).yellow());
-                }
-            }
-            println!("{} {} ({}:{} → {}:{})", 
-                     "✓ Deleted:".green(),
-                     forward.id.dimmed(),
-                     forward.local_port,
-                     forward.host,
-                     forward.remote_port,
-                     forward.host);
-            deleted_count += 1;
-        } else {
-            let error = format!("✗ Not found: {}", id);
-            eprintln!("{}", error.red());
-            errors.push(error);
-        }
-    }
-    
-    if deleted_count > 0 {
-        config.save()?;
-        println!("\n{}", format!("✓ Deleted {} forward(s)", deleted_count).green());
+    let ports = match (&args.host, &args.ports) {
+        (Some(ports), None) => ports.clone(),
+        (Some(_), Some(_)) => anyhow::bail!("--env {} replaces the HOST argument; pass only the ports", name),
+        _ => anyhow::bail!("PORTS is required"),
+    };
+    let host = match std::env::var(name) {
+        Ok(host) if !host.trim().is_empty() => host.trim().to_string(),
+        Ok(_) => anyhow::bail!("Environment variable {} (from --env) is empty", name),
+        Err(_) => anyhow::bail!("Environment variable {} (from --env) is not set", name),
+    };
+    Ok((host, ports))
+}
+
+/// Refuse a privileged local port unless it was explicitly allowed, since
+/// ssh would otherwise fail to bind it only once the tunnel is starting.
+fn check_privileged_port(local_port: u16, allowed: bool, out: &OutputConfig) -> Result<()> {
+    if !port::requires_privilege(local_port) || port::has_privilege() {
+        return Ok(());
     }
-    
-    if !errors.is_empty() {
-        anyhow::bail!("Some deletions failed");
+    if !allowed {
+        anyhow::bail!(
+            "Local port {} is below {}; on Linux the bind will fail without elevated privileges. \
+             Run pfm with sudo, choose a port of {} or above, or pass --allow-privileged \
+             if this system lets you bind it",
+            local_port, port::FIRST_UNPRIVILEGED_PORT, port::FIRST_UNPRIVILEGED_PORT
+        );
     }
-    
+    warning!(out, "{}", format!(
+        "⚠ Warning: local port {} is below {}; the bind fails on Linux unless run with sudo",
+        local_port, port::FIRST_UNPRIVILEGED_PORT
+    ).yellow());
     Ok(())
 }
 
-fn kill_process(pid: u32) -> Result<()> {
-    let output = std::process::Command::new("kill")
-        .arg(pid.to_string())
-        .output()
-        .context("Failed to execute kill command")?;
+/// Validate `pfm add` arguments and turn them into a [`NewForward`].
+fn new_forward(config: &Config, args: &AddArgs, out: &OutputConfig) -> Result<NewForward> {
+    let (host, ports) = resolve_host_and_ports(args)?;
+    let spec = manager::parse_ports(&ports)?;
+    let (local_port, remote_port) = (spec.local, spec.remote);
+    let remote_host = match (spec.remote_host, &args.remote_host) {
+        (Some(from_ports), Some(flag)) if from_ports != *flag => anyhow::bail!(
+            "Remote host given twice: '{}' in the ports and '{}' via --remote-host", from_ports, flag
+        ),
+        (from_ports, flag) => from_ports.or_else(|| flag.clone()),
+    };
+    check_privileged_port(local_port, args.allow_privileged, out)?;
 
-    if output.status.success() {
-        println!("  Stopped process: {}", pid);
-        Ok(())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if stderr.contains("No such process") {
-            println!("    Process {} was already stopped", pid);
-            Ok(())
-        } else {
-            anyhow::bail!("Failed to kill process {}:{}", pid, stderr)
+    let mut ssh_options = args.ssh_options.clone();
+    if args.batch {
+        match ssh_options.iter().find(|o| o.to_ascii_lowercase().starts_with("batchmode=")) {
+            Some(option) if !option.eq_ignore_ascii_case(ssh::BATCH_MODE_OPTION) => {
+                anyhow::bail!("--batch conflicts with -o {}", option);
+            }
+            Some(_) => {}
+            None => ssh_options.push(ssh::BATCH_MODE_OPTION.to_string()),
+        }
+    }
+
+    Ok(NewForward {
+        id: args.id.clone(),
+        host,
+        user: args.user.clone(),
+        local_port,
+        remote_port,
+        remote_host,
+        ssh_port: args.ssh_port,
+        connect_timeout: args.connect_timeout,
+        tags: args.tags.clone(),
+        ssh_options,
+        bind_addr: args.bind.clone(),
+        gateway: args.gateway,
+        multiplex: args.multiplex,
+        log: args.log,
+        comment: args.comment.clone(),
+        allow_remap: !args.no_remap,
+        port_range: args.port_range,
+        ready_timeout: Duration::from_secs(args.timeout),
+        retry: SshRetryPolicy {
+            attempts: args.retries.or(args.retry_attempts).unwrap_or(config.settings.retry.attempts),
+            delay_ms: args.retry_delay_ms.unwrap_or(config.settings.retry.delay_ms),
+            backoff: args.retries.is_some(),
+        },
+        startup_wait: args.startup_timeout
+            .unwrap_or(config.settings.startup_wait()),
+    })
+}
+
+/// Stored forwards, with their indices, that reach the same remote end as `new`.
+fn same_destination<'a>(config: &'a Config, new: &NewForward) -> Vec<(usize, &'a PortForward)> {
+    let target = effective_target(&new.host, new.user.as_deref());
+    let remote_host = new.remote_host.as_deref().unwrap_or(DEFAULT_REMOTE_HOST);
+    config.get_sorted_forwards()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, f)| {
+            f.target() == target && f.remote_port == new.remote_port && f.remote_host() == remote_host
+        })
+        .collect()
+}
+
+fn add_forward(config: &mut Config, args: &AddArgs, out: &OutputConfig) -> Result<()> {
+    let new = new_forward(config, args, out)?;
+
+    let same_target = same_destination(config, &new);
+    if args.if_not_exists
+        && let Some((index, _)) = same_target.iter().find(|(_, f)| f.local_port == new.local_port) {
+        info!(out, "{}", format!("Forward already exists (index {})", index).dimmed());
+        return Ok(());
+    }
+    if !same_target.is_empty() && !args.force {
+        for (index, forward) in &same_target {
+            warning!(out, "{}", format!("⚠ {}:{} is already forwarded by index {} (local port {})",
+                                   forward.target(), forward.remote_port, index, forward.local_port).yellow());
+        }
+        if !confirm("Add another forward to the same destination?", "--force")? {
+            info!(out, "{}", "Nothing added".dimmed());
+            return Err(Exit::nothing_to_do());
+        }
+    }
+
+    let bind_addr = new.bind_addr.as_deref().unwrap_or(DEFAULT_BIND_ADDR);
+    if port::port_status(bind_addr, new.local_port) == port::PortStatus::InUse {
+        info!(out, "{}", format!("Port {} is already in use", new.local_port).yellow());
+    }
+
+    let (added, tunnel) = manager::add_forward_attached(config, new, out)?;
+    config.save()?;
+    let forward = &added.forward;
+
+    info!(out, "\n{}", "✓ Port forward created!".green().bold());
+    info!(out, "{}", format!("  ID: {}", forward.id).cyan());
+    match &forward.remote_host {
+        Some(remote_host) => info!(out, "  {}:{} → {} → {}:{}",
+                                   local_host(forward).dimmed(),
+                                   forward.local_port.to_string().cyan(),
+                                   forward.host.cyan(),
+                                   remote_host.cyan(),
+                                   forward.remote_port.to_string().cyan()),
+        None => info!(out, "  {}:{} → {}:{}", 
+                      local_host(forward).dimmed(), 
+                      forward.local_port.to_string().cyan(), 
+                      forward.host.cyan(), 
+                      forward.remote_port.to_string().cyan()),
+    }
+    info!(out, "  {}: {}", "PID".cyan(), forward.pid.unwrap_or_default());
+
+    if added.was_remapped() {
+            info!(out, "{}", format!("\n⚠ Port remapped from {} to {}", added.requested_port, forward.local_port).yellow());
+        }
+
+    if args.wait {
+        wait_in_foreground(config.active_profile(), &forward.id, tunnel, out);
+    } else {
+        std::mem::forget(tunnel);
+    }
+    Ok(())
+}
+
+/// A line of an `add-file` file, parsed with the same options as `pfm add`.
+#[derive(Parser)]
+#[command(name = "add")]
+struct AddFileLine {
+    #[command(flatten)]
+    args: AddArgs,
+}
+
+fn add_from_file(config: &mut Config, path: &std::path::Path, out: &OutputConfig) -> Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let (lines, invalid) = pfm::add_file::parse(&contents);
+
+    let mut failed = invalid.len();
+    for line in &invalid {
+        eprintln!("{}", format!("✗ Line {}: {}", line.line, line.reason).red());
+    }
+    let (mut added, mut skipped) = (0, 0);
+    for line in &lines {
+        match add_file_line(config, &line.words, out) {
+            Ok(Some(forward)) => {
+                let remote = match &forward.remote_host {
+                    Some(remote_host) => format!("{} → {}:{}", forward.target(), remote_host, forward.remote_port),
+                    None => format!("{}:{}", forward.target(), forward.remote_port),
+                };
+                info!(out, "{} Line {}: {} ({}:{} → {})", "✓".green(), line.line, forward.id,
+                      local_host(&forward), forward.local_port, remote);
+                added += 1;
+            }
+            Ok(None) => {
+                info!(out, "{}", format!("- Line {}: already exists", line.line).dimmed());
+                skipped += 1;
+            }
+            Err(e) => {
+                eprintln!("{}", format!("✗ Line {}: {:#}", line.line, e).red());
+                failed += 1;
+            }
+        }
+    }
+
+    if added > 0 {
+        config.save()?;
+    }
+    info!(out, "\n{} added, {} already existed, {} failed", added.to_string().green(), skipped, failed);
+    if failed > 0 {
+        return Err(Exit::partial(format!("{} line(s) of {} failed", failed, path.display())));
+    }
+    Ok(())
+}
+
+/// Add the forward described by one line of an `add-file` file. Returns `None`
+/// for an `--if-not-exists` line whose forward is already there. Nothing is
+/// asked interactively, so a duplicate destination needs `--force`.
+fn add_file_line(config: &mut Config, words: &[String], out: &OutputConfig) -> Result<Option<PortForward>> {
+    let args = AddFileLine::try_parse_from(std::iter::once("add").chain(words.iter().map(String::as_str)))
+        .map_err(|e| {
+            let message = e.to_string();
+            let first = message.lines().next().unwrap_or_default();
+            anyhow::anyhow!("{}", first.trim_start_matches("error: "))
+        })?
+        .args;
+    if args.wait {
+        anyhow::bail!("--wait is not supported in a file");
+    }
+
+    let new = new_forward(config, &args, out)?;
+    let same_target = same_destination(config, &new);
+    if args.if_not_exists && same_target.iter().any(|(_, f)| f.local_port == new.local_port) {
+        return Ok(None);
+    }
+    if let Some((index, forward)) = same_target.first()
+        && !args.force {
+        anyhow::bail!("{}:{} is already forwarded by index {}; add --force to the line to add another",
+                      forward.target(), forward.remote_port, index);
+    }
+    Ok(Some(manager::add_forward(config, new, out)?.forward))
+}
+
+/// Set by the SIGINT/SIGTERM handler that `add --wait` installs.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Turn SIGINT and SIGTERM into the `INTERRUPTED` flag instead of exiting.
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+/// Without a handler Ctrl-C ends pfm at once; the entry is left behind as a
+/// dead forward for `pfm cleanup`.
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
+/// Removes a `--wait` forward from the saved config when dropped, including
+/// while unwinding from a panic. The config is reloaded first since other pfm
+/// commands may have saved it while the tunnel was up.
+struct ForegroundForward {
+    profile: String,
+    id: String,
+    out: OutputConfig,
+}
+
+impl Drop for ForegroundForward {
+    fn drop(&mut self) {
+        let out = &self.out;
+        let removed = Config::load().and_then(|mut config| {
+            config.use_profile(&self.profile);
+            config.remove_forward(&self.id);
+            config.save()
+        });
+        match removed {
+            Ok(()) => info!(out, "{} {}", "✓ Stopped and removed:".green(), self.id.dimmed()),
+            Err(e) => warning!(out, "{}", format!("⚠ Failed to remove {}: {:#}", self.id, e).yellow()),
         }
     }
 }
-fn cleanup_dead_forwards(config: &mut Config) -> Result<()> {
-    let mut removed_count = 0;
-    let dead_ids: Vec<String> = config
-        .forwards
-        .values()
-        .filter(|f| {
-            if let Some(pid) = f.pid {
-                !port::is_process_running(pid)
+
+/// Block until Ctrl-C, then kill the tunnel and forget the forward. If ssh
+/// exits in the meantime it is reconnected in place, backing off while it keeps failing.
+fn wait_in_foreground(profile: &str, id: &str, mut tunnel: SshTunnel, out: &OutputConfig) {
+    let _cleanup = ForegroundForward { profile: profile.to_string(), id: id.to_string(), out: *out };
+    install_interrupt_handler();
+    info!(out, "\n{}", "Running in the foreground; press Ctrl-C to stop".dimmed());
+
+    let mut failures = 0;
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        if !tunnel.has_exited() {
+            std::thread::sleep(Duration::from_millis(200));
+            continue;
+        }
+        if failures > 0 {
+            let delay = manager::backoff_delay(failures);
+            warning!(out, "{}", format!("  Retrying in {}s", delay.as_secs()).yellow());
+            if !sleep_unless_interrupted(delay) {
+                break;
+            }
+        }
+        warning!(out, "{}", "⚠ SSH process exited; reconnecting".yellow());
+        match tunnel.reconnect() {
+            Ok(pid) => {
+                failures = 0;
+                info!(out, "{} {} (PID: {})", "✓ Reconnected:".green(), id.dimmed(), pid);
+                if let Err(e) = record_pid(profile, id, pid) {
+                    warning!(out, "{}", format!("⚠ Failed to record the new pid: {:#}", e).yellow());
+                }
+            }
+            Err(e) => {
+                failures += 1;
+                warning!(out, "{}", format!("⚠ Reconnect failed: {:#}", e).yellow());
+            }
+        }
+    }
+    drop(tunnel);
+}
+
+/// Sleep for `duration`, returning early (with false) on Ctrl-C.
+fn sleep_unless_interrupted(duration: Duration) -> bool {
+    let step = Duration::from_millis(200);
+    let mut slept = Duration::ZERO;
+    while slept < duration {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return false;
+        }
+        std::thread::sleep(step);
+        slept += step;
+    }
+    !INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Save `pid` as the forward's process, reloading the config first since other
+/// pfm commands may have saved it since.
+fn record_pid(profile: &str, id: &str, pid: u32) -> Result<()> {
+    let mut config = Config::load()?;
+    config.use_profile(profile);
+    if let Some(forward) = config.forwards_mut().get_mut(id) {
+        forward.pid = Some(pid);
+        config.save()?;
+    }
+    Ok(())
+}
+
+
+fn duplicate_forward(
+    config: &mut Config,
+    id: &str,
+    local_port: Option<u16>,
+    timeout: Duration,
+    out: &OutputConfig,
+) -> Result<()> {
+    let source = config.resolve_forward(id)
+        .with_context(|| format!("Forward not found: {}", id))?
+        .clone();
+    let source_id = source.id.clone();
+
+    let local = match local_port {
+        Some(port) => {
+            if !port::is_port_available(&source.bind_addr, port) {
+                anyhow::bail!("Port {} is already in use", port);
+            }
+            port
+        }
+        // Stopped forwards keep their port, so skip configured ports as well as bound ones
+        None => match config.settings.default_port_range {
+            Some(range) => range.find_available(&source.bind_addr, &config.configured_ports())?,
+            None => port::find_available_port(
+                &source.bind_addr,
+                source.local_port.saturating_add(1),
+                config.settings.port_search_attempts,
+                &config.configured_ports(),
+            )?,
+        },
+    };
+
+    let new = NewForward {
+        user: source.user,
+        remote_host: source.remote_host,
+        ssh_port: source.ssh_port,
+        connect_timeout: source.connect_timeout,
+        tags: source.tags,
+        ssh_options: source.ssh_options,
+        bind_addr: Some(source.bind_addr),
+        gateway: source.gateway,
+        multiplex: source.multiplex,
+        log: source.log_enabled,
+        comment: source.comment,
+        ready_timeout: timeout,
+        retry: config.settings.retry,
+        startup_wait: config.settings.startup_wait(),
+        ..NewForward::new(source.host, local, source.remote_port)
+    };
+    let forward = manager::add_forward(config, new, out)?.forward;
+    config.save()?;
+
+    info!(out, "\n{}", "✓ Port forward duplicated!".green().bold());
+    info!(out, "{}", format!("  ID: {}", forward.id).cyan());
+    info!(out, "  {}:{} → {} → {}:{}",
+             local_host(&forward).dimmed(),
+             forward.local_port.to_string().cyan(),
+             forward.host.cyan(),
+             forward.remote_host().cyan(),
+             forward.remote_port.to_string().cyan());
+    info!(out, "  {}: {}", "Cloned from".cyan(), source_id);
+    info!(out, "  {}: {}", "PID".cyan(), forward.pid.unwrap_or_default());
+    Ok(())
+}
+
+/// Returns how many dead forwards could not be revived.
+fn restart_dead_forwards(config: &mut Config, timeout: Duration, out: &OutputConfig) -> Result<usize> {
+    let report = manager::restart_dead_forwards(config, timeout, out);
+    print_restart_report(config, &report, ("Revived", "revive"), out)
+}
+
+/// Returns how many stopped forwards could not be started.
+fn start_stopped_forwards(config: &mut Config, timeout: Duration, out: &OutputConfig) -> Result<usize> {
+    let report = manager::start_stopped_forwards(config, timeout, out);
+    if report.revived.is_empty() && report.failed.is_empty() {
+        info!(out, "{}", "Every enabled forward is already running".dimmed());
+        return Ok(0);
+    }
+    print_restart_report(config, &report, ("Started", "start"), out)
+}
+
+/// Print each relaunch outcome and save the config if any tunnel came up.
+/// `verbs` is the past tense and the infinitive, e.g. ("Revived", "revive").
+fn print_restart_report(
+    config: &mut Config,
+    report: &manager::RestartReport,
+    (done, verb): (&str, &str),
+    out: &OutputConfig,
+) -> Result<usize> {
+    for (id, pid) in &report.revived {
+        info!(out, "{} {} (PID: {})", format!("✓ {}:", done).green(), id.dimmed(), pid);
+    }
+    for (id, error) in &report.failed {
+        eprintln!("{}", format!("✗ Failed to {} {}: {}", verb, id, error).red());
+    }
+
+    if !report.revived.is_empty() {
+        config.save()?;
+        info!(out, "\n{}", format!("✓ {} {} forward(s)", done, report.revived.len()).green());
+    }
+
+    Ok(report.failed.len())
+}
+
+const DEFAULT_SUMMARY_FORMAT: &str = "{running}/{total}";
+
+fn list_forwards(config: &mut Config, args: &ListArgs, out: &OutputConfig) -> Result<()> {
+    let sorted = config.get_sorted_forwards();
+    let states = manager::running_states(&sorted);
+    let seen: Vec<String> = sorted.iter()
+        .zip(&states)
+        .filter(|&(_, &running)| running)
+        .map(|(f, _)| f.id.clone())
+        .collect();
+    if manager::record_seen(config, &seen, manager::unix_now()) {
+        config.save()?;
+    }
+
+    let sorted = config.get_sorted_forwards();
+    let visible: Vec<Row> = sorted.into_iter()
+        .zip(states)
+        .enumerate()
+        .map(|(index, (forward, running))| (index, forward, running))
+        .filter(|&(_, f, running)| args.matches(f, running))
+        .collect();
+    if let Some(format) = &args.summary {
+        let running = visible.iter().filter(|&&(_, _, running)| running).count();
+        let total = visible.len();
+        println!("{}", format.as_deref().unwrap_or(DEFAULT_SUMMARY_FORMAT)
+            .replace("{running}", &running.to_string())
+            .replace("{stopped}", &(total - running).to_string())
+            .replace("{total}", &total.to_string()));
+        return Ok(());
+    }
+    let rows = sort_forwards(visible, args.sort_by);
+
+    match args.output_format() {
+        OutputFormat::Table if args.group_by.is_some() => render_grouped_by_host(config, args, &rows, out),
+        _ if args.group_by.is_some() => anyhow::bail!("--group-by only applies to --format table"),
+        OutputFormat::Table => render_table(config, args, &rows, out),
+        OutputFormat::Json => render_json(&rows),
+        OutputFormat::Csv => render_csv(&rows),
+        OutputFormat::Porcelain => render_porcelain(&rows),
+    }
+    Ok(())
+}
+
+/// A listed forward: its index, the forward, and whether it is running.
+type Row<'a> = (usize, &'a PortForward, bool);
+
+/// Print the heading with running and total counts, or a hint when there are
+/// no forwards at all. Returns whether there is anything to list.
+fn print_list_header(config: &Config, args: &ListArgs, rows: &[Row], out: &OutputConfig) -> bool {
+    if config.forwards().is_empty() {
+        info!(out, "{}", "No port forwards configured.".yellow());
+        info!(out, "\n{}", "Add one with: pfm add <host> <ports>".dimmed());
+        return false;
+    }
+
+    let total = config.forwards().len();
+    let running = rows.iter()
+        .filter(|&&(_, _, running)| running)
+        .count();
+
+    if args.is_filtered() {
+        info!(out, "\n{} ({} running, {} matching, {} total)\n",
+                 "Port forwards:".bold().underline(),
+                 running.to_string().green(),
+                 rows.len(),
+                 total);
+    } else {
+        info!(out, "\n{} ({} running, {} total)\n", 
+                 "Port forwards:".bold().underline(),
+                 running.to_string().green(),
+                 total);
+    }
+    true
+}
+
+fn render_table(config: &Config, args: &ListArgs, rows: &[Row], out: &OutputConfig) {
+    if !print_list_header(config, args, rows, out) {
+        return;
+    }
+
+    for &(index, forward, running) in rows {
+        let mut id_line = format!("  {}: {}", "ID".cyan(), index.to_string().bold());
+        if forward.log_enabled {
+            id_line.push_str(" 📝");
+        }
+        if !forward.enabled {
+            id_line.push_str(&format!(" {}", "(disabled)".dimmed()));
+        }
+        println!("{}", id_line);
+        println!("  {}:  {}", "Host".cyan(), forward.target());
+        match &forward.remote_host {
+            Some(remote_host) => println!("  {}: {} → {}:{}",
+                                          "Ports".cyan(),
+                                          forward.local_end(),
+                                          remote_host,
+                                          forward.remote_port),
+            None => println!("  {}: {} → {}", 
+                             "Ports".cyan(), 
+                             forward.local_end(), 
+                             forward.remote_port),
+        }
+        if let Some(requested) = forward.requested_local_port {
+            println!("  {}: {}", "Remap".cyan(),
+                     format!("requested {}, using {}", requested, forward.local_port).yellow());
+        }
+        if !forward.tags.is_empty() {
+            println!("  {}:  {}", "Tags".cyan(), forward.tags.join(", ").magenta());
+        }
+        if forward.gateway {
+            println!("  {}:  {}", "Bind".cyan(), "all interfaces (GatewayPorts)".yellow());
+        }
+        if let Some(comment) = &forward.comment {
+            println!("  {}:  {}", "Note".cyan(), truncate(comment, COMMENT_PREVIEW_LEN).italic());
+        }
+
+        if let Some(pid) = forward.pid {
+            let status = if running {
+                "● Running".green()
             } else {
-                false
+                "○ Stopped".yellow()
+            };
+            println!("  {}:   {} ({})", "PID".cyan(), pid, status);
+        }
+        if running && let Some(last_active) = forward.last_active_at {
+            println!("  {}:  {}", "Used".cyan(),
+                     format_age(manager::unix_now().saturating_sub(last_active)).dimmed());
+        }
+        if !running && let Some(last_seen) = forward.last_seen_at {
+            println!("  {}:  {}", "Seen".cyan(),
+                     format_age(manager::unix_now().saturating_sub(last_seen)).dimmed());
+        }
+        
+        println!();
+    }
+}
+
+/// Like [`render_table`], but with one heading per host and a line per forward
+/// under it. Hosts are in alphabetical order; forwards keep the `--sort-by` order.
+fn render_grouped_by_host(config: &Config, args: &ListArgs, rows: &[Row], out: &OutputConfig) {
+    if !print_list_header(config, args, rows, out) {
+        return;
+    }
+
+    let mut by_host: BTreeMap<&str, Vec<Row>> = BTreeMap::new();
+    for &row in rows {
+        by_host.entry(row.1.host.as_str()).or_default().push(row);
+    }
+
+    for (host, forwards) in by_host {
+        println!("  {} ({})", host.cyan().bold(), forwards.len());
+        for (index, forward, running) in forwards {
+            let ports = match &forward.remote_host {
+                Some(remote_host) => format!("{} → {}:{}", forward.local_end(), remote_host, forward.remote_port),
+                None => format!("{} → {}", forward.local_end(), forward.remote_port),
+            };
+            let status = match (forward.pid, running) {
+                (Some(pid), true) => format!("{} {}", "● Running".green(), pid),
+                (Some(pid), false) => format!("{} {}", "○ Stopped".yellow(), pid),
+                (None, _) => "○ Stopped".yellow().to_string(),
+            };
+            let mut line = format!("    {}  {:<24} {}", format!("{:>3}", index).bold(), ports, status);
+            if let Some(user) = &forward.user {
+                line.push_str(&format!("  {}", format!("as {}", user).dimmed()));
             }
-        })
-        .map(|f| f.id.clone())
+            if !forward.enabled {
+                line.push_str(&format!(" {}", "(disabled)".dimmed()));
+            }
+            println!("{}", line);
+        }
+        println!();
+    }
+}
+
+/// Host name to reach the forward's local port on: `localhost` unless it
+/// listens on a specific non-loopback or IPv6 address.
+fn local_host(forward: &PortForward) -> String {
+    match port::connect_addr(&forward.bind_addr) {
+        IpAddr::V4(addr) if addr.is_loopback() => "localhost".to_string(),
+        IpAddr::V6(addr) => format!("[{}]", addr),
+        addr => addr.to_string(),
+    }
+}
+
+fn render_json(rows: &[Row]) {
+    let entries: Vec<serde_json::Value> = rows.iter()
+        .map(|&(index, forward, running)| serde_json::json!({
+            "index": index,
+            "id": forward.id,
+            "host": forward.host,
+            "user": forward.user,
+            "bind_addr": forward.bind_addr,
+            "local_port": forward.local_port,
+            "requested_local_port": forward.requested_local_port,
+            "remote_port": forward.remote_port,
+            "remote_host": forward.remote_host(),
+            "pid": forward.pid,
+            "running": running,
+            "last_seen_at": forward.last_seen_at,
+            "last_active_at": forward.last_active_at,
+            "enabled": forward.enabled,
+            "tags": forward.tags,
+            "comment": forward.comment,
+        }))
         .collect();
-    
-    for id in dead_ids {
-        if let Some(forward) = config.remove_forward(&id) {
-            println!("{} {} (PID: {})", 
-                     "✓ Removed dead forward:".yellow(),
-                     forward.id.dimmed(), 
-                     forward.pid.unwrap());
-            removed_count += 1
+    println!("{}", serde_json::to_string_pretty(&entries).unwrap_or_default());
+}
+
+/// Quote a CSV field when it contains a delimiter, quote, or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_csv(rows: &[Row]) {
+    println!("index,id,host,local_port,remote_port,pid,running,enabled,tags,comment");
+    for &(index, forward, running) in rows {
+        println!("{},{},{},{},{},{},{},{},{},{}",
+                 index,
+                 csv_field(&forward.id),
+                 csv_field(&forward.target()),
+                 forward.local_port,
+                 forward.remote_port,
+                 forward.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+                 running,
+                 forward.enabled,
+                 csv_field(&forward.tags.join(";")),
+                 csv_field(forward.comment.as_deref().unwrap_or("")));
+    }
+}
+
+/// Tab-separated and stable across patch releases:
+/// index, id, host, local port, remote port, pid (or -), running.
+fn render_porcelain(rows: &[Row]) {
+    for &(index, forward, running) in rows {
+        println!("{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                 index,
+                 forward.id,
+                 forward.target(),
+                 forward.local_port,
+                 forward.remote_port,
+                 forward.pid.map_or("-".to_string(), |pid| pid.to_string()),
+                 running);
+    }
+}
+
+/// Stable sort so ties keep the index order from `get_sorted_forwards`.
+fn sort_forwards(mut forwards: Vec<Row>, key: SortKey) -> Vec<Row> {
+    match key {
+        SortKey::Index => {}
+        SortKey::Id => forwards.sort_by(|(_, a, _), (_, b, _)| a.id.cmp(&b.id)),
+        SortKey::Host => forwards.sort_by(|(_, a, _), (_, b, _)| a.host.cmp(&b.host)),
+        SortKey::LocalPort => forwards.sort_by_key(|(_, f, _)| f.local_port),
+        SortKey::RemotePort => forwards.sort_by_key(|(_, f, _)| f.remote_port),
+        SortKey::Status => forwards.sort_by_key(|&(_, _, running)| !running),
+        SortKey::Created => forwards.sort_by_key(|(_, f, _)| f.created_at.unwrap_or(u64::MAX)),
+    }
+    forwards
+}
+
+fn format_age(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h ago", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m ago", hours, mins)
+    } else if mins > 0 {
+        format!("{}m ago", mins)
+    } else {
+        format!("{}s ago", secs)
+    }
+}
+
+const COMMENT_PREVIEW_LEN: usize = 60;
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let head: String = text.chars().take(max_chars - 1).collect();
+        format!("{}…", head)
+    }
+}
+
+struct WatchOptions {
+    interval: Duration,
+    notifier: Option<Notifier>,
+    /// Relaunch dead forwards with exponential backoff
+    follow: bool,
+    ready_timeout: Duration,
+    /// Sample client connections to record when each forward was last used
+    track_activity: bool,
+    /// Report events as JSON Lines on stdout
+    json: bool,
+}
+
+/// Print one watch event as a JSON line, if JSON events were requested.
+fn emit_event(options: &WatchOptions, event: &str, profile: &str, id: &str, pid: Option<u32>, reason: &str) {
+    use std::io::Write;
+
+    if !options.json {
+        return;
+    }
+    let line = serde_json::json!({
+        "timestamp": manager::unix_now(),
+        "event": event,
+        "profile": profile,
+        "id": id,
+        "pid": pid,
+        "reason": reason,
+    });
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+/// Consecutive reconnect attempts for a forward that has not yet stayed up
+/// for a whole watch interval.
+struct Backoff {
+    failures: u32,
+    next_attempt: std::time::Instant,
+}
+
+/// Re-read the config every `interval` and report forwards whose process has died.
+/// Each death is reported once; a forward that comes back is watched again.
+fn watch_forwards(profile: &str, options: &WatchOptions, out: &OutputConfig) -> Result<()> {
+    use std::collections::{HashMap, HashSet};
+    use std::time::Instant;
+
+    info!(out, "Watching profile '{}' every {}s (Ctrl-C to stop)", profile, options.interval.as_secs());
+    let mut reported: HashSet<(String, u32)> = HashSet::new();
+    // Tunnels already announced with a `started` or `reconnected` event
+    let mut announced: HashSet<(String, u32)> = HashSet::new();
+    let mut backoffs: HashMap<String, Backoff> = HashMap::new();
+    loop {
+        let mut config = Config::load()?;
+        config.use_profile(profile);
+
+        let watched: Vec<PortForward> = config.get_sorted_forwards()
+            .into_iter()
+            .filter(|f| f.enabled)
+            .cloned()
+            .collect();
+        let mut relaunched: Vec<PortForward> = Vec::new();
+        let mut active: Vec<String> = Vec::new();
+
+        for forward in &watched {
+            let Some(pid) = forward.pid else { continue };
+            if forward.is_running() {
+                // Surviving a full interval counts as a stable connection.
+                reported.remove(&(forward.id.clone(), pid));
+                backoffs.remove(&forward.id);
+                if announced.insert((forward.id.clone(), pid)) {
+                    emit_event(options, "started", profile, &forward.id, Some(pid), "tunnel is running");
+                }
+                if options.track_activity
+                    && port::active_connections(forward.local_port).is_some_and(|count| count > 0)
+                {
+                    active.push(forward.id.clone());
+                }
+                continue;
+            }
+
+            if reported.insert((forward.id.clone(), pid)) {
+                emit_event(options, "died", profile, &forward.id, Some(pid), "ssh process is no longer running");
+                warning!(out, "{} {} (pid {}, {}:{} → {}:{})",
+                         "✗ Tunnel died:".red(), forward.id, pid,
+                         local_host(forward), forward.local_port, forward.target(), forward.remote_port);
+                if let Some(notifier) = &options.notifier
+                    && let Err(e) = notifier.tunnel_died(profile, forward)
+                {
+                    warning!(out, "{} {:#}", "⚠ Notification failed:".yellow(), e);
+                }
+            }
+
+            if !options.follow {
+                continue;
+            }
+            let backoff = backoffs.entry(forward.id.clone()).or_insert(Backoff {
+                failures: 0,
+                next_attempt: Instant::now(),
+            });
+            if Instant::now() < backoff.next_attempt {
+                continue;
+            }
+
+            backoff.failures += 1;
+            let delay = manager::backoff_delay(backoff.failures);
+            backoff.next_attempt = Instant::now() + delay;
+            emit_event(options, "reconnecting", profile, &forward.id, Some(pid),
+                       &format!("attempt {}", backoff.failures));
+            match manager::relaunch_forward(&mut config, &forward.id, options.ready_timeout, out) {
+                Ok(new_pid) => {
+                    relaunched.extend(config.forwards().get(&forward.id).cloned());
+                    announced.insert((forward.id.clone(), new_pid));
+                    emit_event(options, "reconnected", profile, &forward.id, Some(new_pid),
+                               &format!("relaunched after {} attempt(s)", backoff.failures));
+                    info!(out, "{} {} (PID: {})", "✓ Relaunched:".green(), forward.id, new_pid);
+                }
+                Err(e) => {
+                    emit_event(options, "reconnect_failed", profile, &forward.id, None,
+                               &format!("{:#}; retrying in {}s", e, delay.as_secs()));
+                    warning!(out, "{} {}: {:#}", "✗ Relaunch failed:".red(), forward.id, e);
+                    info!(out, "  {}", format!("Retrying {} in {}s (attempt {})",
+                                          forward.id, delay.as_secs(), backoff.failures + 1).dimmed());
+                }
+            }
+        }
+
+        if !relaunched.is_empty() || !active.is_empty() {
+            save_watch_results(profile, &relaunched, &active)?;
         }
+        std::thread::sleep(options.interval);
     }
-    
-    if removed_count > 0 {
+}
+
+/// Write a watch pass's relaunched tunnels and activity stamps into a freshly
+/// loaded config, so commands that saved while tunnels were starting are kept.
+/// A relaunched forward that was deleted meanwhile has its new tunnel stopped.
+fn save_watch_results(profile: &str, relaunched: &[PortForward], active: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    config.use_profile(profile);
+
+    let mut changed = manager::record_active(&mut config, active, manager::unix_now());
+    for launched in relaunched {
+        match config.forwards_mut().get_mut(&launched.id) {
+            Some(forward) => {
+                forward.pid = launched.pid;
+                forward.local_port = launched.local_port;
+                forward.requested_local_port = launched.requested_local_port;
+                forward.log_file = launched.log_file.clone();
+                changed = true;
+            }
+            None => {
+                if let Some(pid) = launched.pid {
+                    let _ = port::terminate(pid);
+                }
+            }
+        }
+    }
+    if changed {
         config.save()?;
-        println!("\n{}", format!("✓ Cleaned up {} dead forward(s)", removed_count).green());
+    }
+    Ok(())
+}
+
+fn monitor_pid_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("monitor.pid"))
+}
+
+/// The pid of the background monitor, if its pid file points at a live process
+/// that is still a monitor. A pid reused by an unrelated process does not count;
+/// when the command line cannot be read, a live pid is trusted.
+fn running_monitor() -> Result<Option<u32>> {
+    let Ok(contents) = std::fs::read_to_string(monitor_pid_path()?) else {
+        return Ok(None);
+    };
+    Ok(contents.trim().parse().ok().filter(|&pid| {
+        port::is_process_running(pid)
+            && port::process_command_line(pid).is_none_or(|command| command.contains("monitor --foreground"))
+    }))
+}
+
+/// Re-run this executable as `monitor --foreground`, detached from the terminal.
+fn start_monitor(profile: &str, interval: Duration, timeout: Duration, out: &OutputConfig) -> Result<()> {
+    if let Some(pid) = running_monitor()? {
+        anyhow::bail!("Monitor is already running (pid {}); stop it with: pfm monitor stop", pid);
+    }
+
+    let dir = Config::config_dir()?;
+    std::fs::create_dir_all(&dir).context("Failed to create config directory")?;
+    let log_path = dir.join("monitor.log");
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("Failed to open {}", log_path.display()))?;
+
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command
+        .args(["--profile", profile, "monitor", "--foreground"])
+        .args(["--interval", &interval.as_secs().to_string()])
+        .args(["--timeout", &timeout.as_secs().to_string()])
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // A process group of its own keeps Ctrl-C and terminal hangups from reaching it.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let child = command.spawn().context("Failed to start monitor")?;
+    std::fs::write(monitor_pid_path()?, child.id().to_string())
+        .context("Failed to write monitor.pid")?;
+
+    info!(out, "{} pid {} (profile '{}', every {}s)",
+          "✓ Monitor started:".green(), child.id(), profile, interval.as_secs());
+    info!(out, "  {} {}", "Log:".dimmed(), log_path.display());
+    Ok(())
+}
+
+fn stop_monitor(out: &OutputConfig) -> Result<()> {
+    match running_monitor()? {
+        Some(pid) => {
+            port::kill_process(pid, out)?;
+            info!(out, "{} pid {}", "✓ Monitor stopped:".green(), pid);
+        }
+        None => info!(out, "{}", "Monitor is not running".dimmed()),
+    }
+    let _ = std::fs::remove_file(monitor_pid_path()?);
+    Ok(())
+}
+
+fn monitor_status() -> Result<()> {
+    match running_monitor()? {
+        Some(pid) => println!("{} (pid {})", "● Running".green(), pid),
+        None => println!("{}", "○ Stopped".yellow()),
+    }
+    Ok(())
+}
+
+fn open_forward(config: &Config, index: usize, path: Option<&str>, out: &OutputConfig) -> Result<()> {
+    let forward = config.get_forward_by_index(index)
+        .with_context(|| format!("Invalid index: {}", index))?;
+
+    if !forward.is_running() {
+        warning!(out, "{} {} is not running; opening anyway",
+                 "⚠ Warning:".yellow(), forward.id);
+    }
+
+    let path = match path {
+        Some(path) if !path.starts_with('/') => format!("/{}", path),
+        Some(path) => path.to_string(),
+        None => String::new(),
+    };
+    let url = format!("http://{}:{}{}", local_host(forward), forward.local_port, path);
+    info!(out, "Opening {}", url);
+    open::that(&url).context("Failed to launch a browser")
+}
+
+/// Replace pfm with an interactive `ssh` to the forward's host. On platforms
+/// without `exec`, wait for ssh and exit with its status instead.
+fn connect_shell(config: &Config, id: &str, out: &OutputConfig) -> Result<()> {
+    let forward = config.resolve_forward(id)
+        .with_context(|| format!("Forward not found: {}", id))?;
+    let mut command = std::process::Command::new("ssh");
+    command.args(SshTunnel::shell_args(forward));
+    info!(out, "Connecting to {}", forward.target());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = command.exec();
+        Err(error).context("Failed to run ssh")
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command.status().context("Failed to run ssh")?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+fn rename_forward(config: &mut Config, index_or_id: &str, new_id: &str, out: &OutputConfig) -> Result<()> {
+    let id = config.resolve_forward(index_or_id)
+        .with_context(|| format!("Forward not found: {}", index_or_id))?
+        .id.clone();
+    manager::rename_forward(config, &id, new_id)?;
+    config.save()?;
+    info!(out, "{} {} → {}", "✓ Renamed:".green(), id.dimmed(), new_id);
+    Ok(())
+}
+
+fn move_forward(config: &mut Config, index: usize, to_profile: &str, out: &OutputConfig) -> Result<()> {
+    let id = config.get_forward_by_index(index)
+        .with_context(|| format!("Invalid index: {}", index))?
+        .id.clone();
+    let created = !config.has_profile(to_profile);
+    manager::move_forward(config, &id, to_profile)?;
+    config.save()?;
+    if created {
+        info!(out, "{} {}", "✓ Created profile:".green(), to_profile);
+    }
+    info!(out, "{} {} → profile {}", "✓ Moved:".green(), id.dimmed(), to_profile);
+    Ok(())
+}
+
+fn set_forward_enabled(
+    config: &mut Config,
+    index_or_id: &str,
+    enabled: bool,
+    out: &OutputConfig,
+) -> Result<()> {
+    let id = config.resolve_forward(index_or_id)
+        .with_context(|| format!("Forward not found: {}", index_or_id))?
+        .id.clone();
+    let state = if enabled { "enabled" } else { "disabled" };
+
+    if manager::set_enabled(config, &id, enabled)? {
+        config.save()?;
+        info!(out, "{} {} is now {}", "✓".green(), id, state);
+    } else {
+        info!(out, "{}", format!("{} is already {}", id, state).dimmed());
+    }
+    Ok(())
+}
+
+/// Quote `word` for a POSIX shell if it contains anything beyond safe characters.
+fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./-_".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// The `pfm add` invocation that recreates `forward` with all persisted options.
+fn recreate_command(profile: &str, forward: &PortForward) -> String {
+    let mut words = vec!["pfm".to_string()];
+    if profile != DEFAULT_PROFILE {
+        words.extend(["--profile".to_string(), shell_quote(profile)]);
+    }
+    words.push("add".to_string());
+    if !manager::has_generated_id(forward) {
+        words.push(format!("--id {}", shell_quote(&forward.id)));
+    }
+    words.push(shell_quote(&forward.host));
+    if let Some(user) = &forward.user {
+        words.push(format!("--user {}", shell_quote(user)));
+    }
+    words.push(match &forward.remote_host {
+        Some(remote_host) => shell_quote(&format!("{}:{}:{}", forward.local_port, remote_host, forward.remote_port)),
+        None if forward.local_port == forward.remote_port => forward.local_port.to_string(),
+        None => format!("{}:{}", forward.local_port, forward.remote_port),
+    });
+    if let Some(ssh_port) = forward.ssh_port {
+        words.push(format!("--ssh-port {}", ssh_port));
+    }
+    if let Some(secs) = forward.connect_timeout {
+        words.push(format!("--connect-timeout {}", secs));
+    }
+    for tag in &forward.tags {
+        words.push(format!("--tag {}", shell_quote(tag)));
+    }
+    if forward.bind_addr != DEFAULT_BIND_ADDR {
+        words.push(format!("--bind {}", shell_quote(&forward.bind_addr)));
+    }
+    if forward.gateway {
+        words.push("--gateway".to_string());
+    }
+    if forward.multiplex {
+        words.push("--multiplex".to_string());
+    }
+    if port::requires_privilege(forward.local_port) {
+        words.push("--allow-privileged".to_string());
+    }
+    if forward.log_enabled {
+        words.push("--log".to_string());
+    }
+    for option in &forward.ssh_options {
+        words.push(format!("--ssh-option {}", shell_quote(option)));
+    }
+    if let Some(comment) = &forward.comment {
+        words.push(format!("--comment {}", shell_quote(comment)));
+    }
+    words.join(" ")
+}
+
+fn show_logs(config: &Config, id: &str, lines: usize, follow: bool, out: &OutputConfig) -> Result<()> {
+    let forward = config.resolve_forward(id)
+        .with_context(|| format!("Forward not found: {}", id))?;
+    if !forward.log_enabled {
+        anyhow::bail!(
+            "Logging is not enabled for {}; turn it on with `pfm edit {} --log` \
+             and it takes effect the next time the tunnel starts",
+            forward.id, forward.id
+        );
+    }
+    let log_file = forward.log_file.as_deref()
+        .with_context(|| format!(
+            "No log file recorded for {}; ssh output is captured the next time it starts \
+             (e.g. `pfm up`)",
+            forward.id
+        ))?;
+
+    if !log_file.exists() {
+        info!(out, "{}", format!("No output logged yet at {}", log_file.display()).dimmed());
+        if !follow {
+            return Ok(());
+        }
     } else {
-        println!("{}", "No dead forwards found".dimmed());
+        for line in ssh::read_log_tail(log_file, lines)? {
+            println!("{}", line);
+        }
+    }
+
+    if follow {
+        follow_file(log_file)?;
+    }
+    Ok(())
+}
+
+/// Print whatever is appended to `path` until interrupted.
+fn follow_file(path: &std::path::Path) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut offset = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let Ok(mut file) = std::fs::File::open(path) else {
+            continue;
+        };
+        let len = file.metadata()?.len();
+        if len < offset {
+            // The log was rotated or truncated; start over.
+            offset = 0;
+        }
+        if len == offset {
+            continue;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        let mut chunk = Vec::new();
+        file.read_to_end(&mut chunk)?;
+        offset += chunk.len() as u64;
+        io::stdout().write_all(&chunk)?;
+        io::stdout().flush()?;
+    }
+}
+
+fn edit_forward(config: &mut Config, args: &EditArgs, out: &OutputConfig) -> Result<()> {
+    let id = config.resolve_forward(&args.id)
+        .with_context(|| format!("Forward not found: {}", args.id))?
+        .id.clone();
+
+    let edit = ForwardEdit {
+        host: args.host.clone(),
+        local_port: args.local,
+        remote_port: args.remote,
+        ssh_port: args.ssh_port,
+        comment: args.comment.clone(),
+        log: if args.log { Some(true) } else if args.no_log { Some(false) } else { None },
+    };
+    // Save even on failure: a failed restart changes the forward's pid
+    let edited = manager::edit_forward(config, &id, edit, Duration::from_secs(args.timeout), out);
+    config.save()?;
+    let edited = edited?;
+
+    info!(out, "{} {}", "✓ Updated:".green(), edited.forward.id.dimmed());
+    if edited.old_id != edited.forward.id {
+        info!(out, "  {} {} → {}", "ID changed:".yellow(), edited.old_id.dimmed(), edited.forward.id);
+    }
+    if edited.restarted {
+        info!(out, "  Restarted tunnel (PID: {})", edited.forward.pid.unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// Report the forwards bound to local `port` and whether they are alive.
+fn which_port(config: &Config, port: u16) {
+    let mut profiles: Vec<&String> = config.profiles.keys().collect();
+    profiles.sort();
+
+    let mut found = false;
+    for profile in profiles {
+        let mut matches: Vec<&PortForward> = config.profiles[profile].values()
+            .filter(|f| f.local_port == port)
+            .collect();
+        matches.sort_by_key(|f| &f.id);
+        for forward in matches {
+            found = true;
+            let status = if forward.is_running() { "running".green() } else { "stopped".red() };
+            let profile_note = if profile != config.active_profile() {
+                format!(" (profile {})", profile).dimmed().to_string()
+            } else {
+                String::new()
+            };
+            println!(
+                "{} {}:{} -> {}:{} [{}]{}",
+                forward.id.bold(),
+                local_host(forward),
+                forward.local_port,
+                forward.target(),
+                forward.remote_port,
+                status,
+                profile_note
+            );
+        }
+    }
+
+    if !found {
+        println!("No forward uses local port {}", port);
+        if !port::is_port_available_ipv4(port) {
+            println!("{}", format!("Port {} is in use by a process not managed by pfm", port).yellow());
+        }
+    }
+}
+
+fn check_port(config: &Config, port: u16) -> Result<()> {
+    match port::port_status(DEFAULT_BIND_ADDR, port) {
+        port::PortStatus::Available => {
+            println!("{} Port {} is available", "✓".green(), port);
+            return Ok(());
+        }
+        port::PortStatus::PermissionDenied => {
+            println!("{} Port {} needs elevated privileges to bind (try sudo or a port of {} or above)",
+                     "✗".red(), port, port::FIRST_UNPRIVILEGED_PORT);
+            return Err(Exit::reported_failure());
+        }
+        port::PortStatus::InUse => {}
+    }
+
+    println!("{} Port {} is in use", "✗".red(), port);
+    let mut profiles: Vec<&String> = config.profiles.keys().collect();
+    profiles.sort();
+    let owners: Vec<(&String, &PortForward)> = profiles.into_iter()
+        .flat_map(|profile| config.profiles[profile].values().map(move |f| (profile, f)))
+        .filter(|(_, f)| f.local_port == port && f.is_running())
+        .collect();
+    if owners.is_empty() {
+        println!("  by a process not managed by pfm");
+    }
+    for (profile, forward) in owners {
+        let profile_note = if profile != config.active_profile() {
+            format!(", profile {}", profile)
+        } else {
+            String::new()
+        };
+        println!("  by forward {} (pid {}{}) -> {}:{}",
+                 forward.id.bold(),
+                 forward.pid.unwrap_or_default(),
+                 profile_note,
+                 forward.target(),
+                 forward.remote_port);
+    }
+    Err(Exit::reported_failure())
+}
+
+fn show_forward(config: &Config, index: usize) -> Result<()> {
+    let forward = config.get_forward_by_index(index)
+        .with_context(|| format!("Invalid index: {}", index))?;
+
+    let running = forward.is_running();
+
+    println!("  {}:          {}", "Index".cyan(), index.to_string().bold());
+    println!("  {}:             {}", "ID".cyan(), forward.id);
+    if !forward.enabled {
+        println!("  {}:        {}", "Enabled".cyan(), "no".yellow());
+    }
+    println!("  {}:           {}", "Host".cyan(), forward.target());
+    println!("  {}:     {}", "Local port".cyan(), forward.local_port);
+    if let Some(requested) = forward.requested_local_port {
+        println!("  {}: {} (busy when added)", "Requested port".cyan(), requested);
+    }
+    println!("  {}:    {}", "Remote port".cyan(), forward.remote_port);
+    if let Some(remote_host) = &forward.remote_host {
+        println!("  {}:    {}", "Remote host".cyan(), remote_host);
+    }
+    if !forward.tags.is_empty() {
+        println!("  {}:           {}", "Tags".cyan(), forward.tags.join(", ").magenta());
+    }
+    if !forward.ssh_options.is_empty() {
+        println!("  {}:    {}", "SSH options".cyan(), forward.ssh_options.join(" "));
+    }
+    if forward.gateway {
+        println!("  {}:           {}", "Bind".cyan(), "all interfaces (GatewayPorts)".yellow());
+    }
+    if forward.multiplex {
+        println!("  {}:      shared connection (ControlMaster)", "Multiplex".cyan());
+    }
+    if let Some(comment) = &forward.comment {
+        println!("  {}:        {}", "Comment".cyan(), comment);
+    }
+    if let Some(log_file) = &forward.log_file {
+        println!("  {}:       {}", "Log file".cyan(), log_file.display());
+    }
+    if let Some(created_at) = forward.created_at {
+        println!("  {}:        {}", "Created".cyan(),
+                 format_age(manager::unix_now().saturating_sub(created_at)));
+    }
+    if let Some(last_seen) = forward.last_seen_at {
+        println!("  {}:      {}", "Last seen".cyan(),
+                 format_age(manager::unix_now().saturating_sub(last_seen)));
+    }
+    if let Some(last_active) = forward.last_active_at {
+        println!("  {}:      {}", "Last used".cyan(),
+                 format_age(manager::unix_now().saturating_sub(last_active)));
+    }
+    match forward.pid {
+        Some(pid) => {
+            let status = if running {
+                "● Running".green()
+            } else {
+                "○ Stopped".yellow()
+            };
+            println!("  {}:            {} ({})", "PID".cyan(), pid, status);
+        }
+        None => println!("  {}:            {}", "PID".cyan(), "none".dimmed()),
+    }
+    match &forward.ssh_command {
+        Some(command) => println!("  {}:    {}", "SSH command".cyan(), command),
+        None => println!("  {}:    {} {}", "SSH command".cyan(),
+                         SshTunnel::build_command_line(forward),
+                         "(reconstructed)".dimmed()),
+    }
+
+    // `ps` is unavailable on Windows, so its details are best-effort.
+    if let Some(pid) = forward.pid.filter(|_| running)
+        && let Ok(output) = std::process::Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", "pid,ppid,etime,pcpu,pmem,command"])
+            .output()
+        && output.status.success()
+    {
+        println!("\n{}", "Process:".bold().underline());
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            println!("  {}", line);
+        }
+    }
+
+    Ok(())
+}
+
+fn delete_forwards(config: &mut Config, ids: &[String], tags: &[String], out: &OutputConfig) -> Result<()> {
+    if ids.len() == 1 && ids[0] == "all" {
+        info!(out, "{}", format!("Deleting all {} forward(s)...\n", config.forwards().len()).yellow());
+    }
+
+    let report = manager::delete_forwards(config, ids, tags);
+
+    for forward in &report.deleted {
+        if let Some((_, pid, termination)) = report.stopped.iter().find(|(id, _, _)| *id == forward.id) {
+            info!(out, "  {}", termination.describe(*pid));
+        }
+        info!(out, "{} {} ({}:{} → {}:{})", 
+                 "✓ Deleted:".green(),
+                 forward.id.dimmed(),
+                 forward.local_port,
+                 forward.target(),
+                 forward.remote_port,
+                 forward.target());
+    }
+    for warning in &report.warnings {
+        warning!(out, "{}", format!("  ⚠ Warning: {}", warning).yellow());
+    }
+    for error in &report.errors {
+        eprintln!("{}", format!("✗ {}", error).red());
     }
     
+    if !report.deleted.is_empty() {
+        config.save()?;
+        info!(out, "\n{}", format!("✓ Deleted {} forward(s)", report.deleted.len()).green());
+    }
+    
+    if !report.errors.is_empty() {
+        return Err(Exit::partial("Some deletions failed"));
+    }
+    
+    Ok(())
+}
+
+/// Print `contents` to stdout, or write it to `path` when given.
+fn write_output(contents: &str, path: Option<&std::path::Path>, out: &OutputConfig) -> Result<()> {
+    match path {
+        Some(path) => {
+            std::fs::write(path, contents)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            info!(out, "{} {}", "✓ Wrote".green(), path.display());
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+fn export_forwards(
+    config: &Config,
+    file: Option<&std::path::Path>,
+    format: ExportFormat,
+    include_labels: bool,
+    out: &OutputConfig,
+) -> Result<()> {
+    let contents = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&config.to_portable())
+            .context("Failed to serialize config")?,
+        ExportFormat::SshConfig => config.get_sorted_forwards()
+            .into_iter()
+            .map(|forward| {
+                let alias = if include_labels && !manager::has_generated_id(forward) {
+                    forward.id.clone()
+                } else {
+                    format!("pfm-{}", forward.id)
+                };
+                // ssh treats these as pattern characters in a Host line
+                let alias = alias.replace(['*', '?', '!'], "_");
+                pfm::ssh_config::render_host_block(&alias, forward)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    write_output(&format!("{}\n", contents), file, out)
+}
+
+fn import_forwards(
+    config: &mut Config,
+    file: &std::path::Path,
+    mode: ImportMode,
+    out: &OutputConfig,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let imported = Config::from_json(&contents)
+        .with_context(|| format!("Failed to parse {}", file.display()))?;
+
+    let report = manager::import_forwards(config, imported, mode);
+    config.save()?;
+
+    for (profile, id) in &report.imported {
+        info!(out, "{} {} {}", "✓ Imported:".green(), id, format!("[{}]", profile).dimmed());
+    }
+    for (profile, id) in &report.skipped {
+        warning!(out, "{}", format!("⚠ Skipped existing id: {} [{}]", id, profile).yellow());
+    }
+    info!(out, "\n{}", format!("✓ Imported {} forward(s), skipped {}",
+                           report.imported.len(), report.skipped.len()).green());
+    Ok(())
+}
+
+/// Add the forwards defined in a `.env`-style file. They are only registered
+/// unless `start` is set; lines that cannot be used are skipped with a warning.
+fn import_env_file(
+    config: &mut Config,
+    path: &std::path::Path,
+    start: bool,
+    out: &OutputConfig,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let (entries, invalid) = pfm::env_file::parse(&contents);
+    for line in &invalid {
+        warning!(out, "{}", format!("⚠ Skipped line {}: {}", line.line, line.reason).yellow());
+    }
+    if entries.is_empty() {
+        anyhow::bail!("No {}<n> entries found in {}", pfm::env_file::KEY_PREFIX, path.display());
+    }
+
+    let mut added = 0;
+    for entry in entries {
+        let result = manager::parse_ports(&entry.ports).and_then(|spec| {
+            let new = NewForward {
+                remote_host: spec.remote_host,
+                allow_remap: true,
+                ready_timeout: Duration::from_secs(10),
+                retry: config.settings.retry,
+                startup_wait: config.settings.startup_wait(),
+                ..NewForward::new(entry.host.clone(), spec.local, spec.remote)
+            };
+            if start {
+                manager::add_forward(config, new, out).map(|added| added.forward)
+            } else {
+                manager::register_forward(config, new)
+            }
+        });
+        match result {
+            Ok(forward) => {
+                let verb = if start { "✓ Started:" } else { "✓ Registered:" };
+                info!(out, "{} {}", verb.green(), forward.id);
+                added += 1;
+            }
+            Err(e) => warning!(out, "{}", format!("⚠ Skipped {} (line {}): {:#}", entry.key, entry.line, e).yellow()),
+        }
+    }
+
+    if added > 0 {
+        config.save()?;
+    }
+    if !start && added > 0 {
+        info!(out, "{}", "Start them with: pfm up".dimmed());
+    }
+    Ok(())
+}
+
+fn import_from_ssh_config(
+    config: &mut Config,
+    path: Option<&std::path::Path>,
+    remote_port: u16,
+    out: &OutputConfig,
+) -> Result<()> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => dirs::home_dir()
+            .context("Could not determine home directory")?
+            .join(".ssh")
+            .join("config"),
+    };
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let entries = pfm::ssh_config::parse(&contents);
+    if entries.is_empty() {
+        anyhow::bail!("No concrete Host entries found in {}", path.display());
+    }
+
+    println!("{} {}\n", "Hosts in".bold(), path.display());
+    for (index, entry) in entries.iter().enumerate() {
+        println!("  {}: {} {}", index.to_string().bold(), entry.alias.cyan(), entry.target().dimmed());
+    }
+    print!("\nSelect hosts to add (e.g. `0 2`, or `all`): ");
+    io::Write::flush(&mut io::stdout())?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let selected: Vec<&pfm::ssh_config::SshHostEntry> = if answer.trim() == "all" {
+        entries.iter().collect()
+    } else {
+        answer.split_whitespace()
+            .map(|token| token.parse::<usize>().ok()
+                .and_then(|index| entries.get(index))
+                .with_context(|| format!("Invalid selection: {}", token)))
+            .collect::<Result<_>>()?
+    };
+
+    let mut added = 0;
+    for entry in selected {
+        let new = NewForward {
+            retry: config.settings.retry,
+            ..NewForward::new(entry.alias.clone(), remote_port, remote_port)
+        };
+        match manager::register_forward(config, new) {
+            Ok(forward) => {
+                info!(out, "{} {}", "✓ Registered:".green(), forward.id);
+                added += 1;
+            }
+            Err(e) => warning!(out, "{}", format!("⚠ Skipped {}: {:#}", entry.alias, e).yellow()),
+        }
+    }
+
+    if added > 0 {
+        config.save()?;
+    }
+    info!(out, "\n{}", format!("✓ Registered {} forward(s); start them with `pfm up`", added).green());
+    Ok(())
+}
+
+fn manage_profiles(config: &mut Config, command: &ProfileCommands, out: &OutputConfig) -> Result<()> {
+    match command {
+        ProfileCommands::List => {
+            let mut names: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+            if !names.contains(&DEFAULT_PROFILE) {
+                names.push(DEFAULT_PROFILE);
+            }
+            names.sort();
+
+            for name in names {
+                let count = config.profiles.get(name).map(|f| f.len()).unwrap_or(0);
+                let marker = if name == config.active_profile() { "*" } else { " " };
+                println!("{} {} ({} forward(s))", marker.green(), name.bold(), count);
+            }
+        }
+        ProfileCommands::New { name } => {
+            if config.has_profile(name) {
+                anyhow::bail!("Profile '{}' already exists", name);
+            }
+            config.profiles.insert(name.clone(), Default::default());
+            config.save()?;
+            info!(out, "{} {}", "✓ Created profile:".green(), name);
+        }
+        ProfileCommands::Delete { name } => {
+            if name == DEFAULT_PROFILE {
+                anyhow::bail!("The default profile cannot be deleted");
+            }
+            let forwards = config.profiles.get(name)
+                .with_context(|| format!("Profile '{}' does not exist", name))?;
+            if !forwards.is_empty() {
+                anyhow::bail!("Profile '{}' still has {} forward(s); delete them first", name, forwards.len());
+            }
+            config.profiles.remove(name);
+            config.save()?;
+            info!(out, "{} {}", "✓ Deleted profile:".green(), name);
+        }
+    }
+    Ok(())
+}
+
+fn check_health(
+    config: &Config,
+    ids: &[String],
+    http_path: Option<&str>,
+    timeout: Duration,
+) -> Result<()> {
+    let forwards: Vec<&PortForward> = if ids.is_empty() {
+        config.get_sorted_forwards()
+    } else {
+        ids.iter()
+            .map(|id| config.resolve_forward(id)
+                .with_context(|| format!("Forward not found: {}", id)))
+            .collect::<Result<_>>()?
+    };
+
+    let mut unhealthy = 0;
+    for forward in forwards {
+        let result = probe::tcp(forward.local_addr(), timeout).and_then(|elapsed| {
+            match http_path {
+                Some(path) => probe::http(forward.local_addr(), path, timeout)
+                    .map(|status| format!("HTTP {} in {:?}", status, elapsed)),
+                None => Ok(format!("connected in {:?}", elapsed)),
+            }
+        });
+
+        match result {
+            Ok(detail) => println!("{} {} (:{}) {}",
+                                   "● healthy  ".green(),
+                                   forward.id,
+                                   forward.local_port,
+                                   detail.dimmed()),
+            Err(e) => {
+                unhealthy += 1;
+                println!("{} {} (:{}) {}",
+                         "○ unhealthy".red(),
+                         forward.id,
+                         forward.local_port,
+                         format!("{:#}", e).dimmed());
+            }
+        }
+    }
+
+    if unhealthy > 0 {
+        return Err(Exit::partial(format!("{} forward(s) unhealthy", unhealthy)));
+    }
+    Ok(())
+}
+
+fn ping_forward(config: &Config, id: &str, count: u8, timeout: Duration) -> Result<()> {
+    let forward = config.resolve_forward(id)
+        .with_context(|| format!("Forward not found: {}", id))?;
+    if !forward.is_running() {
+        anyhow::bail!("Forward {} is not running", forward.id);
+    }
+
+    let addr = forward.local_addr();
+    println!("Connecting to {} ({} → {}:{})",
+             addr, forward.id, forward.target(), forward.remote_port);
+    let mut samples = Vec::new();
+    for seq in 1..=count {
+        match probe::tcp(addr, timeout) {
+            Ok(elapsed) => {
+                println!("  connect {}: {:.2} ms", seq, millis(elapsed));
+                samples.push(elapsed);
+            }
+            Err(e) => println!("  connect {}: {}", seq, format!("{:#}", e).red()),
+        }
+    }
+
+    let failed = usize::from(count) - samples.len();
+    let Some(latency) = probe::Latency::from_samples(&samples) else {
+        anyhow::bail!("All {} connect(s) to {} failed", count, addr);
+    };
+    println!("{} connected, {} failed; min/avg/max = {:.2}/{:.2}/{:.2} ms",
+             samples.len(), failed, millis(latency.min), millis(latency.avg), millis(latency.max));
+    if failed > 0 {
+        return Err(Exit::partial(format!("{} of {} connect(s) failed", failed, count)));
+    }
     Ok(())
 }
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Ask a yes/no question on stderr; anything but "y" or "yes" (including EOF) is no.
+/// Without a terminal nobody can answer, so that is an error pointing at
+/// `override_flag`, which skips the question.
+fn confirm(question: &str, override_flag: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("{} Cannot ask without a terminal; pass {} to proceed", question, override_flag);
+    }
+    eprint!("{} [y/N] ", question);
+    io::Write::flush(&mut io::stderr())?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn collect_inactive_forwards(
+    config: &mut Config,
+    all_stopped: bool,
+    yes: bool,
+    out: &OutputConfig,
+) -> Result<()> {
+    let ids = manager::find_inactive_forwards(config, all_stopped);
+    if ids.is_empty() {
+        info!(out, "{}", "No inactive forwards found".dimmed());
+        return Err(Exit::nothing_to_do());
+    }
+
+    println!("{}", "Inactive forwards:".bold());
+    for id in &ids {
+        let forward = &config.forwards()[id];
+        println!("  {} ({}:{} → {}:{})",
+                 id, local_host(forward), forward.local_port, forward.target(), forward.remote_port);
+    }
+
+    if !yes && !confirm(&format!("Remove {} forward(s)?", ids.len()), "--yes")? {
+        info!(out, "{}", "Nothing removed".dimmed());
+        return Err(Exit::nothing_to_do());
+    }
+
+    delete_forwards(config, &ids, &[], out)
+}
+
+const DRY_RUN_PREFIX: &str = "[DRY RUN] ";
+
+/// Returns whether there was anything to clean up.
+fn cleanup_dead_forwards(config: &mut Config, dry_run: bool, out: &OutputConfig) -> Result<bool> {
+    let removed: Vec<PortForward> = if dry_run {
+        manager::find_dead_forwards(config)
+            .iter()
+            .filter_map(|id| config.forwards().get(id).cloned())
+            .collect()
+    } else {
+        manager::cleanup_dead_forwards(config).removed
+    };
+    let prefix = if dry_run { DRY_RUN_PREFIX } else { "" };
+
+    for forward in &removed {
+        info!(out, "{}{} {} (PID: {})", 
+                 prefix,
+                 "✓ Removed dead forward:".yellow(),
+                 forward.id.dimmed(), 
+                 forward.pid.unwrap_or_default());
+    }
+    
+    if removed.is_empty() {
+        info!(out, "{}", "No dead forwards found".dimmed());
+    } else if dry_run {
+        info!(out, "\n{}", format!("{}Would clean up {} dead forward(s)", prefix, removed.len()).dimmed());
+    } else {
+        config.save()?;
+        info!(out, "\n{}", format!("✓ Cleaned up {} dead forward(s)", removed.len()).green());
+    }
+    
+    Ok(!removed.is_empty())
+}
+
+/// List ssh processes that look like orphaned tunnels and, after confirmation
+/// (or with `force`), kill them. Returns whether any were found.
+fn reap_orphans_of(config: &Config, dry_run: bool, force: bool, out: &OutputConfig) -> Result<bool> {
+    let orphans = manager::find_orphans(config);
+    if orphans.is_empty() {
+        info!(out, "{}", "No orphaned ssh processes found".dimmed());
+        return Ok(false);
+    }
+    let prefix = if dry_run { DRY_RUN_PREFIX } else { "" };
+    for (pid, id) in &orphans {
+        info!(out, "{}{} pid {} (runs the tunnel of {})", prefix, "Orphan candidate:".yellow(), pid, id.dimmed());
+    }
+    if dry_run {
+        return Ok(true);
+    }
+    let question = format!(
+        "Kill {} process(es)? Tunnels started by a systemd/launchd unit or by hand look the same.",
+        orphans.len()
+    );
+    if !force && !confirm(&question, "--force")? {
+        info!(out, "{}", "No processes killed".dimmed());
+        return Ok(true);
+    }
+    for (pid, id) in orphans {
+        match port::kill_process(pid, out) {
+            Ok(()) => info!(out, "{} pid {} (duplicate of {})", "✓ Reaped orphan:".yellow(), pid, id.dimmed()),
+            Err(e) => warning!(out, "{}", format!("⚠ Failed to reap pid {}: {:#}", pid, e).yellow()),
+        }
+    }
+    Ok(true)
+}
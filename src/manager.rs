@@ -0,0 +1,1573 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::config::{effective_target, expand_env, strip_user, Config, PortForward, Settings, DEFAULT_BIND_ADDR, DEFAULT_REMOTE_HOST};
+use crate::output::{warning, OutputConfig};
+use crate::port;
+use crate::ssh::{self, SshRetryPolicy, SshTunnel};
+
+/// Parameters for a forward that has not been started yet.
+#[derive(Debug, Clone)]
+pub struct NewForward {
+    /// Explicit id; generated from the host and ports when `None`
+    pub id: Option<String>,
+    pub host: String,
+    /// Login user; takes precedence over a `user@` prefix in `host`
+    pub user: Option<String>,
+    pub local_port: u16,
+    pub remote_port: u16,
+    /// Host the ssh server forwards to; `None` for the server itself
+    pub remote_host: Option<String>,
+    pub ssh_port: Option<u16>,
+    pub connect_timeout: Option<u64>,
+    pub tags: Vec<String>,
+    pub ssh_options: Vec<String>,
+    /// Address the local port listens on; [`DEFAULT_BIND_ADDR`] when `None`
+    pub bind_addr: Option<String>,
+    /// Let other hosts connect to the local port
+    pub gateway: bool,
+    /// Share one ssh connection with other multiplexed forwards to the same host
+    pub multiplex: bool,
+    /// Capture ssh's stderr in the forward's log file
+    pub log: bool,
+    pub comment: Option<String>,
+    /// Pick the next free port if `local_port` is taken, instead of failing
+    pub allow_remap: bool,
+    /// Ports a remap may pick from; the config's default range, if any, when `None`
+    pub port_range: Option<port::PortRange>,
+    pub ready_timeout: Duration,
+    pub retry: SshRetryPolicy,
+    /// How long to watch ssh for an early exit on each attempt
+    pub startup_wait: Duration,
+}
+
+impl NewForward {
+    /// A forward from `local_port` to `remote_port` on `host` with every other
+    /// option unset or at its default.
+    pub fn new(host: impl Into<String>, local_port: u16, remote_port: u16) -> Self {
+        NewForward {
+            id: None,
+            host: host.into(),
+            user: None,
+            local_port,
+            remote_port,
+            remote_host: None,
+            ssh_port: None,
+            connect_timeout: None,
+            tags: Vec::new(),
+            ssh_options: Vec::new(),
+            bind_addr: None,
+            gateway: false,
+            multiplex: false,
+            log: false,
+            comment: None,
+            allow_remap: false,
+            port_range: None,
+            ready_timeout: Duration::ZERO,
+            retry: SshRetryPolicy::default(),
+            startup_wait: Duration::ZERO,
+        }
+    }
+}
+
+/// A forward that was started and saved by [`add_forward`].
+#[derive(Debug, Clone)]
+pub struct AddedForward {
+    pub forward: PortForward,
+    /// The local port that was asked for, before any remapping.
+    pub requested_port: u16,
+}
+
+impl AddedForward {
+    pub fn was_remapped(&self) -> bool {
+        self.requested_port != self.forward.local_port
+    }
+}
+
+/// Changes to apply to a stored forward; `None` leaves a field unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct ForwardEdit {
+    pub host: Option<String>,
+    pub local_port: Option<u16>,
+    pub remote_port: Option<u16>,
+    pub ssh_port: Option<u16>,
+    /// `Some("")` clears the comment
+    pub comment: Option<String>,
+    /// Turn stderr capture on or off; takes effect the next time the tunnel starts
+    pub log: Option<bool>,
+}
+
+impl ForwardEdit {
+    fn changes_tunnel(&self) -> bool {
+        self.host.is_some()
+            || self.local_port.is_some()
+            || self.remote_port.is_some()
+            || self.ssh_port.is_some()
+    }
+}
+
+/// Result of [`edit_forward`].
+#[derive(Debug, Clone)]
+pub struct EditedForward {
+    pub old_id: String,
+    pub forward: PortForward,
+    /// Whether a running tunnel was restarted with the new parameters
+    pub restarted: bool,
+}
+
+/// How [`import_forwards`] combines imported forwards with existing ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Keep existing forwards; imported forwards whose id already exists are skipped
+    Merge,
+    /// Drop all existing forwards first
+    Replace,
+}
+
+/// Outcome of [`import_forwards`], as `(profile, id)` pairs.
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: Vec<(String, String)>,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Outcome of [`delete_forwards`]; deletions that succeeded are kept even if others failed.
+#[derive(Debug, Clone, Default)]
+pub struct DeleteReport {
+    pub deleted: Vec<PortForward>,
+    pub errors: Vec<String>,
+    /// How each deleted forward's tunnel was stopped, as `(id, pid, termination)`
+    pub stopped: Vec<(String, u32, port::Termination)>,
+    /// Problems stopping a deleted forward's tunnel; the forward is still removed
+    pub warnings: Vec<String>,
+}
+
+/// Outcome of [`cleanup_dead_forwards`].
+#[derive(Debug, Clone, Default)]
+pub struct CleanupReport {
+    pub removed: Vec<PortForward>,
+}
+
+/// Outcome of [`restart_dead_forwards`] and [`start_stopped_forwards`].
+#[derive(Debug, Clone, Default)]
+pub struct RestartReport {
+    /// Ids of revived forwards with their new pids
+    pub revived: Vec<(String, u32)>,
+    /// Ids of forwards that could not be restarted, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Parse a human duration such as `30s`, `30m`, `2h`, or `7d`.
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let split_at = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split_at);
+    let value: u64 = number.parse()
+        .with_context(|| format!("Invalid duration '{}': expected e.g. 30m, 2h, 7d", text))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => anyhow::bail!("Invalid duration unit in '{}': use s, m, h, d, or w", text),
+    };
+    Ok(Duration::from_secs(value * unit_secs))
+}
+
+/// Parse a non-negative, possibly fractional number of seconds such as `0.5`.
+pub fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value.parse::<f64>().ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("'{}' is not a non-negative number of seconds that fits a duration", value))
+}
+
+/// Ids of forwards created more than `age` ago, optionally only those not running.
+/// Forwards without a creation time are never selected.
+pub fn forwards_older_than(config: &Config, age: Duration, stopped_only: bool) -> Vec<String> {
+    let cutoff = unix_now().saturating_sub(age.as_secs());
+    config.get_sorted_forwards()
+        .into_iter()
+        .filter(|f| f.created_at.is_some_and(|created| created < cutoff))
+        .filter(|f| !stopped_only || !f.is_running())
+        .map(|f| f.id.clone())
+        .collect()
+}
+
+/// Reject hosts ssh could never connect to: empty, containing whitespace,
+/// or with an empty user or hostname around `@`.
+pub fn validate_host(host: &str) -> Result<()> {
+    if host.is_empty() {
+        anyhow::bail!("Host must not be empty");
+    }
+    if host.contains(char::is_whitespace) {
+        anyhow::bail!("Host '{}' must not contain whitespace", host);
+    }
+    if let Some((user, hostname)) = host.rsplit_once('@') {
+        if user.is_empty() {
+            anyhow::bail!("Host '{}' has an empty user before '@'", host);
+        }
+        if hostname.is_empty() {
+            anyhow::bail!("Host '{}' has an empty hostname after '@'", host);
+        }
+    }
+    Ok(())
+}
+
+/// How long [`warn_if_unresolvable`] waits on DNS before giving up on the check.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Warn when the hostname part of `host` does not resolve. This is only a
+/// hint: ssh config aliases are valid targets that DNS knows nothing about.
+/// A lookup slower than [`RESOLVE_TIMEOUT`] is left to finish in the background
+/// and no warning is printed, so slow DNS never holds up `add`.
+fn warn_if_unresolvable(host: &str, out: &OutputConfig) {
+    use std::net::ToSocketAddrs;
+
+    let hostname = strip_user(host).to_string();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let lookup = hostname.clone();
+    std::thread::spawn(move || {
+        let _ = sender.send((lookup.as_str(), 22).to_socket_addrs().is_ok());
+    });
+    if receiver.recv_timeout(RESOLVE_TIMEOUT) == Ok(false) {
+        warning!(out, "{}", format!(
+            "  ⚠ Warning: '{}' does not resolve; ssh will only reach it if it is an ssh config alias",
+            hostname
+        ).yellow());
+    }
+}
+
+/// Whether `forward` still has the id pfm generated for it (possibly with a
+/// `-<n>` suffix) rather than one chosen with `--id`.
+pub fn has_generated_id(forward: &PortForward) -> bool {
+    let generated = forward_id(&forward.target(), forward.local_port, forward.remote_port);
+    forward.id == generated || forward.id.starts_with(&format!("{}-", generated))
+}
+
+pub fn forward_id(host: &str, local: u16, remote: u16) -> String {
+    format!("{}_{}_{}",
+        host.replace("@", "_at_"),
+        local,
+        remote)
+}
+
+/// The ports argument of `pfm add`, split into its parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortSpec {
+    pub local: u16,
+    pub remote: u16,
+    /// Host the ssh server forwards to, if the spec named one
+    pub remote_host: Option<String>,
+}
+
+/// Parse `PORT`, `LOCAL:REMOTE`, or `LOCAL:HOST:REMOTE`.
+pub fn parse_ports(ports: &str) -> Result<PortSpec> {
+    let parse = |which: &str, port: &str| {
+        port.parse::<u16>()
+            .with_context(|| format!("Invalid {} port '{}' in '{}'", which, port, ports))
+    };
+    let parts: Vec<&str> = ports.split(':').collect();
+    match parts[..] {
+        [port] => {
+            let port = parse("local", port)?;
+            Ok(PortSpec { local: port, remote: port, remote_host: None })
+        }
+        [local, remote] => Ok(PortSpec {
+            local: parse("local", local)?,
+            remote: parse("remote", remote)?,
+            remote_host: None,
+        }),
+        [local, host, remote] => {
+            let local = parse("local", local)?;
+            let remote = parse("remote", remote)?;
+            validate_remote_host(host)?;
+            Ok(PortSpec { local, remote, remote_host: Some(host.to_string()) })
+        }
+        _ => anyhow::bail!(
+            "Invalid ports '{}': {} parts, but at most 3 are allowed. Use PORT, LOCAL:REMOTE or LOCAL:HOST:REMOTE",
+            ports, parts.len()
+        ),
+    }
+}
+
+/// Reject remote hosts that would break the `-L LOCAL:HOST:REMOTE` argument.
+pub fn validate_remote_host(host: &str) -> Result<()> {
+    if host.is_empty() {
+        anyhow::bail!("Remote host must not be empty");
+    }
+    if host.contains(|c: char| c.is_whitespace() || c == ':') {
+        anyhow::bail!("Remote host '{}' must not contain whitespace or ':'", host);
+    }
+    Ok(())
+}
+
+/// Start a tunnel for `new`, remapping the local port if it is taken, and record it.
+/// The caller is responsible for saving the config.
+pub fn add_forward(
+    config: &mut Config,
+    new: NewForward,
+    out: &OutputConfig,
+) -> Result<AddedForward> {
+    let (added, tunnel) = add_forward_attached(config, new, out)?;
+    std::mem::forget(tunnel);
+    Ok(added)
+}
+
+/// Like [`add_forward`], but hand back the tunnel instead of detaching it.
+/// Dropping the returned [`SshTunnel`] kills ssh.
+pub fn add_forward_attached(
+    config: &mut Config,
+    new: NewForward,
+    out: &OutputConfig,
+) -> Result<(AddedForward, SshTunnel)> {
+    let target = effective_target(&new.host, new.user.as_deref());
+    validate_host(&target)?;
+    warn_if_unresolvable(&expand_env(&target)?, out);
+
+    let bind_addr = new.bind_addr.clone().unwrap_or_else(|| DEFAULT_BIND_ADDR.to_string());
+    let requested_port = new.local_port;
+    let mut local = requested_port;
+    if port::port_status(&bind_addr, local) == port::PortStatus::PermissionDenied {
+        anyhow::bail!(
+            "Not permitted to bind local port {}: ports below {} need elevated privileges on Linux. \
+             Run pfm with sudo or use a local port of {} or above",
+            local, port::FIRST_UNPRIVILEGED_PORT, port::FIRST_UNPRIVILEGED_PORT
+        );
+    }
+    if !port::is_port_available(&bind_addr, local) {
+        if !new.allow_remap {
+            anyhow::bail!("Port {} is already in use", local);
+        }
+        local = match new.port_range.or(config.settings.default_port_range) {
+            Some(range) => range.find_available(&bind_addr, &config.configured_ports())
+                .map_err(|e| anyhow::anyhow!("Port {} is already in use. {:#}", local, e))?,
+            None => {
+                let start = local.checked_add(1)
+                    .with_context(|| format!("Port {} is already in use", local))?;
+                port::find_available_port(
+                    &bind_addr, start, config.settings.port_search_attempts, &config.configured_ports(),
+                )?
+            }
+        };
+    }
+
+    let (timeout, retry, startup_wait) = (new.ready_timeout, new.retry, new.startup_wait);
+    let mut forward = stored_forward(config, new, local)?;
+    let tunnel = start_tunnel(&mut forward, timeout, retry, startup_wait, out)?;
+    config.add_forward(forward.clone());
+
+    Ok((AddedForward { forward, requested_port }, tunnel))
+}
+
+/// Record `new` without starting a tunnel; it shows as stopped until relaunched.
+/// The caller is responsible for saving the config.
+pub fn register_forward(config: &mut Config, new: NewForward) -> Result<PortForward> {
+    let target = effective_target(&new.host, new.user.as_deref());
+    validate_host(&target)?;
+    let local = new.local_port;
+    let remote_host = new.remote_host.as_deref().unwrap_or(DEFAULT_REMOTE_HOST);
+    if let Some(existing) = config.forwards().values().find(|f| {
+        f.target() == target
+            && f.local_port == local
+            && f.remote_port == new.remote_port
+            && f.remote_host() == remote_host
+    }) {
+        anyhow::bail!("Forward {} already exists", existing.id);
+    }
+    let forward = stored_forward(config, new, local)?;
+    config.add_forward(forward.clone());
+    Ok(forward)
+}
+
+/// `base`, or `base-2`, `base-3`, ... if that id is already taken in the active profile.
+fn unique_id(config: &Config, base: &str) -> String {
+    let forwards = config.forwards();
+    if !forwards.contains_key(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|id| !forwards.contains_key(id))
+        .expect("a free id suffix exists")
+}
+
+/// Check a user-supplied id: it becomes a map key and a log file name.
+pub fn validate_id(id: &str) -> Result<()> {
+    if id.is_empty() {
+        anyhow::bail!("Id must not be empty");
+    }
+    if id.parse::<usize>().is_ok() {
+        anyhow::bail!("Id '{}' must not be a number; numbers select forwards by index", id);
+    }
+    if id.contains(|c: char| c.is_whitespace() || c == '/' || c == '\\') {
+        anyhow::bail!("Id '{}' must not contain whitespace or path separators", id);
+    }
+    Ok(())
+}
+
+/// `options` plus the configured `ServerAliveInterval`, unless they set their own.
+fn with_default_ssh_options(mut options: Vec<String>, settings: &Settings) -> Vec<String> {
+    if let Some(secs) = settings.server_alive_interval
+        && !options.iter().any(|o| o.to_ascii_lowercase().starts_with("serveraliveinterval=")) {
+        options.push(format!("ServerAliveInterval={}", secs));
+    }
+    options
+}
+
+fn stored_forward(config: &Config, new: NewForward, local: u16) -> Result<PortForward> {
+    let id = match new.id {
+        Some(id) => {
+            validate_id(&id)?;
+            if config.forwards().contains_key(&id) {
+                anyhow::bail!("Forward {} already exists", id);
+            }
+            id
+        }
+        None => {
+            let target = effective_target(&new.host, new.user.as_deref());
+            unique_id(config, &forward_id(&target, local, new.remote_port))
+        }
+    };
+    // With an explicit user, keep only the hostname so the user is stored once.
+    let host = match &new.user {
+        Some(_) => strip_user(&new.host).to_string(),
+        None => new.host,
+    };
+    Ok(PortForward {
+        log_file: if new.log { Some(config.log_path(&id)?) } else { None },
+        id,
+        host,
+        user: new.user,
+        local_port: local,
+        remote_port: new.remote_port,
+        requested_local_port: (new.local_port != local).then_some(new.local_port),
+        remote_host: new.remote_host.filter(|host| host != DEFAULT_REMOTE_HOST),
+        pid: None,
+        ssh_port: new.ssh_port,
+        connect_timeout: new.connect_timeout.or(config.settings.default_connect_timeout),
+        ssh_command: None,
+        tags: new.tags,
+        ssh_options: with_default_ssh_options(new.ssh_options, &config.settings),
+        enabled: true,
+        bind_addr: new.bind_addr.unwrap_or_else(|| DEFAULT_BIND_ADDR.to_string()),
+        gateway: new.gateway,
+        multiplex: new.multiplex,
+        log_enabled: new.log,
+        comment: new.comment,
+        created_at: Some(unix_now()),
+        last_seen_at: None,
+        last_active_at: None,
+    })
+}
+
+/// Start a tunnel for `forward` and record the resulting pid and command line.
+fn start_tunnel(
+    forward: &mut PortForward,
+    timeout: Duration,
+    retry: SshRetryPolicy,
+    startup_wait: Duration,
+    out: &OutputConfig,
+) -> Result<SshTunnel> {
+    let tunnel = SshTunnel::start(forward, timeout, retry, startup_wait, out)?;
+    forward.ssh_command = Some(tunnel.command_line().to_string());
+    forward.pid = Some(tunnel.pid());
+    Ok(tunnel)
+}
+
+/// Start a detached tunnel for `forward` and record its pid and command line.
+fn launch(
+    forward: &mut PortForward,
+    timeout: Duration,
+    retry: SshRetryPolicy,
+    startup_wait: Duration,
+    out: &OutputConfig,
+) -> Result<u32> {
+    let tunnel = start_tunnel(forward, timeout, retry, startup_wait, out)?;
+    let pid = tunnel.pid();
+
+    std::mem::forget(tunnel);
+
+    Ok(pid)
+}
+
+/// Start a new tunnel for a stored forward and record its pid. A forward that
+/// was remapped goes back to its requested port if that has become free.
+/// The caller is responsible for saving the config.
+pub fn relaunch_forward(config: &mut Config, id: &str, timeout: Duration, out: &OutputConfig) -> Result<u32> {
+    let (retry, startup_wait) = (config.settings.retry, config.settings.startup_wait());
+    let default_log = config.log_path(id)?;
+    let forward = config.forwards_mut().get_mut(id)
+        .with_context(|| format!("Forward not found: {}", id))?;
+    if let Some(requested) = forward.requested_local_port
+        && port::is_port_available(&forward.bind_addr, requested) {
+        forward.local_port = requested;
+        forward.requested_local_port = None;
+    }
+    if forward.log_enabled {
+        forward.log_file.get_or_insert(default_log);
+    } else {
+        forward.log_file = None;
+    }
+    if forward.multiplex {
+        // A master that outlived the forward's dead ssh may still hold the port.
+        ssh::cancel_forward(forward);
+    }
+
+    launch(forward, timeout, retry, startup_wait, out)
+}
+
+/// Kill a forward's ssh process, if it is running, and clear its pid; the
+/// forward stays defined. Returns whether it was running.
+/// The caller is responsible for saving the config.
+pub fn stop_forward(config: &mut Config, id: &str, out: &OutputConfig) -> Result<bool> {
+    let forward = config.forwards().get(id)
+        .with_context(|| format!("Forward not found: {}", id))?
+        .clone();
+    let was_running = forward.is_running();
+    if was_running && let Some(pid) = forward.pid {
+        port::kill_process(pid, out)?;
+    }
+    release_from_master(config, &forward);
+    if let Some(forward) = config.forwards_mut().get_mut(id) {
+        forward.pid = None;
+    }
+    Ok(was_running)
+}
+
+/// With multiplexing, the local port is bound by the shared master rather than
+/// the forward's own ssh, so it stays bound after that process is killed. Drop
+/// the listener from the master, or close the master when no other running
+/// forward uses it. Best effort: there may be no master at all.
+fn release_from_master(config: &Config, forward: &PortForward) {
+    if !forward.multiplex {
+        return;
+    }
+    let connection = |f: &PortForward| (f.target(), f.ssh_port);
+    let shared = config.all_forwards().any(|f| {
+        f.multiplex && f.pid.is_some() && f.id != forward.id && connection(f) == connection(forward)
+    });
+    if shared {
+        ssh::cancel_forward(forward);
+    } else {
+        ssh::close_master(forward);
+    }
+}
+
+/// Longest wait between reconnect attempts for a forward that keeps failing.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Delay before the next reconnect after `failures` consecutive failed attempts:
+/// 1s, 2s, 4s, ... capped at [`MAX_BACKOFF`].
+pub fn backoff_delay(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    Duration::from_secs(1 << exponent).min(MAX_BACKOFF)
+}
+
+/// Apply `edit` to the forward with `id`. When the host or ports change a generated
+/// id is regenerated from them (an explicit `--id` is kept). A running tunnel
+/// is stopped and relaunched when its host or ports change. If the relaunch fails
+/// the edit is dropped and the original forward is relaunched, or left stopped.
+/// The caller is responsible for saving the config, also when this fails.
+pub fn edit_forward(
+    config: &mut Config,
+    id: &str,
+    edit: ForwardEdit,
+    timeout: Duration,
+    out: &OutputConfig,
+) -> Result<EditedForward> {
+    let original = config.forwards().get(id)
+        .with_context(|| format!("Forward not found: {}", id))?
+        .clone();
+    let mut forward = original.clone();
+
+    if let Some(host) = &edit.host {
+        validate_host(&effective_target(host, forward.user.as_deref()))?;
+    }
+    if let Some(local) = edit.local_port
+        && local != forward.local_port
+        && !port::is_port_available(&forward.bind_addr, local) {
+        anyhow::bail!("Port {} is already in use", local);
+    }
+
+    let edit_changes_tunnel = edit.changes_tunnel();
+    let restart = edit_changes_tunnel && forward.is_running();
+    let id_is_generated = has_generated_id(&forward);
+
+    if let Some(host) = edit.host {
+        forward.host = host;
+    }
+    if let Some(local) = edit.local_port {
+        forward.local_port = local;
+        forward.requested_local_port = None;
+    }
+    if let Some(remote) = edit.remote_port {
+        forward.remote_port = remote;
+    }
+    if let Some(ssh_port) = edit.ssh_port {
+        forward.ssh_port = Some(ssh_port);
+    }
+    if let Some(comment) = edit.comment {
+        forward.comment = (!comment.is_empty()).then_some(comment);
+    }
+    if let Some(log) = edit.log {
+        forward.log_enabled = log;
+    }
+
+    // Ids the user chose explicitly survive edits; generated ones follow the tunnel.
+    if edit_changes_tunnel && id_is_generated {
+        let base = forward_id(&forward.target(), forward.local_port, forward.remote_port);
+        if base != id {
+            forward.id = unique_id(config, &base);
+        }
+    }
+    if forward.id != id {
+        if config.forwards().contains_key(&forward.id) {
+            anyhow::bail!("Forward {} already exists", forward.id);
+        }
+        let log_file = config.log_path(&forward.id)?;
+        if let Some(old_log) = &forward.log_file {
+            let _ = std::fs::rename(old_log, &log_file);
+        }
+        forward.log_file = Some(log_file);
+    }
+    if !forward.log_enabled {
+        forward.log_file = None;
+    } else if forward.log_file.is_none() {
+        forward.log_file = Some(config.log_path(&forward.id)?);
+    }
+
+    if restart {
+        if let Some(pid) = forward.pid {
+            port::kill_process(pid, out)?;
+        }
+        release_from_master(config, &original);
+        forward.pid = None;
+        let (retry, startup_wait) = (config.settings.retry, config.settings.startup_wait());
+        if let Err(e) = launch(&mut forward, timeout, retry, startup_wait, out) {
+            // The old tunnel is already gone, so bring it back as it was rather
+            // than leave the config pointing at a dead pid.
+            if let (Some(new_log), Some(old_log)) = (&forward.log_file, &original.log_file)
+                && new_log != old_log {
+                let _ = std::fs::rename(new_log, old_log);
+            }
+            let mut original = original;
+            original.pid = None;
+            let outcome = match launch(&mut original, timeout, retry, startup_wait, out) {
+                Ok(pid) => format!("{} was restarted with its previous settings (PID: {})", id, pid),
+                Err(_) => format!("{} is stopped; it could not be restarted with its previous settings either", id),
+            };
+            config.forwards_mut().insert(id.to_string(), original);
+            return Err(e.context(format!("Failed to restart with the new settings, so the edit was not applied. {}", outcome)));
+        }
+    }
+
+    config.remove_forward(id);
+    config.add_forward(forward.clone());
+
+    Ok(EditedForward { old_id: id.to_string(), forward, restarted: restart })
+}
+
+/// Give the forward `id` the id `new_id`, moving its log file along. The id
+/// only names the entry, so a running tunnel is left alone.
+/// The caller is responsible for saving the config.
+pub fn rename_forward(config: &mut Config, id: &str, new_id: &str) -> Result<PortForward> {
+    validate_id(new_id)?;
+    if !config.forwards().contains_key(id) {
+        anyhow::bail!("Forward not found: {}", id);
+    }
+    if new_id == id {
+        anyhow::bail!("Forward {} already has that id", id);
+    }
+    if config.forwards().contains_key(new_id) {
+        anyhow::bail!("Forward {} already exists", new_id);
+    }
+    // Everything that can fail is done before the entry leaves the map.
+    let new_log = config.log_path(new_id)?;
+
+    let mut forward = config.remove_forward(id).expect("forward exists");
+    forward.id = new_id.to_string();
+    if let Some(old_log) = &forward.log_file {
+        let _ = std::fs::rename(old_log, &new_log);
+        forward.log_file = Some(new_log);
+    }
+    config.add_forward(forward.clone());
+    Ok(forward)
+}
+
+/// Move the forward `id` from the active profile into `profile`, creating the
+/// profile if needed. A running tunnel keeps running; only the entry moves.
+/// The caller is responsible for saving the config.
+pub fn move_forward(config: &mut Config, id: &str, profile: &str) -> Result<PortForward> {
+    if profile == config.active_profile() {
+        anyhow::bail!("Forward {} is already in profile '{}'", id, profile);
+    }
+    if !config.forwards().contains_key(id) {
+        anyhow::bail!("Forward not found: {}", id);
+    }
+    if config.profiles.get(profile).is_some_and(|forwards| forwards.contains_key(id)) {
+        anyhow::bail!(
+            "Profile '{}' already has a forward with id {}; rename one of them first (`pfm rename`)",
+            profile, id
+        );
+    }
+    let new_log = Config::profile_log_path(profile, id)?;
+
+    let mut forward = config.remove_forward(id).expect("forward exists");
+    if let Some(old_log) = &forward.log_file {
+        if let Some(parent) = new_log.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::rename(old_log, &new_log);
+        forward.log_file = Some(new_log);
+    }
+    config.profiles.entry(profile.to_string()).or_default().insert(id.to_string(), forward.clone());
+    Ok(forward)
+}
+
+/// Copy every forward in `imported` into `config`, profile by profile.
+/// Imported forwards are treated as stopped. The caller is responsible for saving the config.
+pub fn import_forwards(config: &mut Config, imported: Config, mode: ImportMode) -> ImportReport {
+    if mode == ImportMode::Replace {
+        config.profiles.clear();
+    }
+
+    let mut report = ImportReport::default();
+    let mut profiles: Vec<_> = imported.profiles.into_iter().collect();
+    profiles.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (profile, forwards) in profiles {
+        let existing = config.profiles.entry(profile.clone()).or_default();
+        let mut forwards: Vec<PortForward> = forwards.into_values().collect();
+        forwards.sort_by(|a, b| a.id.cmp(&b.id));
+
+        for mut forward in forwards {
+            if existing.contains_key(&forward.id) {
+                report.skipped.push((profile.clone(), forward.id));
+                continue;
+            }
+            forward.clear_runtime_state();
+            report.imported.push((profile.clone(), forward.id.clone()));
+            existing.insert(forward.id.clone(), forward);
+        }
+    }
+    report
+}
+
+/// Resolve `ids` (indices, raw ids, or the single keyword `all`) plus any
+/// forwards carrying one of `tags`, stop their processes, and remove them.
+/// The caller is responsible for saving the config.
+pub fn delete_forwards(config: &mut Config, ids: &[String], tags: &[String]) -> DeleteReport {
+    let mut report = DeleteReport::default();
+
+    let mut ids_to_delete: Vec<String> = if ids.len() == 1 && ids[0] == "all" {
+        config.forwards().keys().cloned().collect()
+    } else {
+        // Resolve indices to IDs
+        let mut result = Vec::new();
+        for id_str in ids {
+            if let Ok(index) = id_str.parse::<usize>() {
+                if let Some(forward) = config.get_forward_by_index(index) {
+                    result.push(forward.id.clone());
+                } else {
+                    report.errors.push(format!("Invalid index: {}", index));
+                }
+            } else {
+                result.push(id_str.to_string());
+            }
+        }
+        result
+    };
+
+    // Add forwards selected by tag
+    if !tags.is_empty() {
+        for forward in config.get_sorted_forwards() {
+            if forward.matches_tags(tags) && !ids_to_delete.contains(&forward.id) {
+                ids_to_delete.push(forward.id.clone());
+            }
+        }
+    }
+
+    for id in ids_to_delete {
+        if let Some(forward) = config.remove_forward(&id) {
+            if let Some(pid) = forward.pid {
+                if !forward.is_running() && port::is_process_running(pid) {
+                    report.warnings.push(format!(
+                        "{}: pid {} no longer runs this tunnel; leaving it alone", forward.id, pid
+                    ));
+                } else {
+                    match port::terminate(pid) {
+                        Ok(termination) => report.stopped.push((forward.id.clone(), pid, termination)),
+                        Err(e) => report.warnings.push(format!("{}: {:#}", forward.id, e)),
+                    }
+                }
+            }
+            if let Some(log_file) = &forward.log_file {
+                let _ = std::fs::remove_file(log_file);
+            }
+            report.deleted.push(forward);
+        } else {
+            report.errors.push(format!("Not found: {}", id));
+        }
+    }
+    close_unused_masters(config, &report.deleted);
+
+    report
+}
+
+/// Multiplexed forwards among `removed` whose master connection no remaining
+/// forward (in any profile) shares, one per connection.
+pub fn unused_masters<'a>(config: &Config, removed: &'a [PortForward]) -> Vec<&'a PortForward> {
+    let connection = |f: &PortForward| (f.target(), f.ssh_port);
+    let mut unused: Vec<&PortForward> = Vec::new();
+    for forward in removed.iter().filter(|f| f.multiplex) {
+        let shared = config.all_forwards().any(|f| f.multiplex && connection(f) == connection(forward));
+        if !shared && !unused.iter().any(|f| connection(f) == connection(forward)) {
+            unused.push(forward);
+        }
+    }
+    unused
+}
+
+/// Close the master connections that only `removed` were using, which removes
+/// their control sockets, and drop the listeners of removed forwards from masters
+/// that stay open. Best effort: a master that already exited is fine.
+fn close_unused_masters(config: &Config, removed: &[PortForward]) {
+    let unused = unused_masters(config, removed);
+    let connection = |f: &PortForward| (f.target(), f.ssh_port);
+    for forward in removed.iter().filter(|f| f.multiplex) {
+        if !unused.iter().any(|master| connection(master) == connection(forward)) {
+            ssh::cancel_forward(forward);
+        }
+    }
+    for forward in unused {
+        ssh::close_master(forward);
+    }
+}
+
+/// ssh processes running a configured forward's tunnel (in any profile) under a
+/// pid no forward has recorded, e.g. left behind by a crash. Returns the pid
+/// and the id of the forward it duplicates. These are only candidates: a tunnel
+/// run by a generated systemd/launchd unit or started by hand matches as well.
+pub fn find_orphans(config: &Config) -> Vec<(u32, String)> {
+    orphans_among(config, port::list_processes())
+}
+
+/// [`find_orphans`] over an explicit process list. Multiplex masters are never
+/// candidates: ControlPersist forks them with the client's argv, and killing
+/// one would drop every multiplexed forward to that host.
+fn orphans_among(config: &Config, processes: Vec<(u32, String)>) -> Vec<(u32, String)> {
+    processes
+        .into_iter()
+        .filter(|(pid, command)| {
+            !command.contains("ControlMaster=") && !config.all_forwards().any(|f| f.pid == Some(*pid))
+        })
+        .filter_map(|(pid, command)| {
+            config.all_forwards()
+                .find(|f| f.matches_command(&command))
+                .map(|f| (pid, f.id.clone()))
+        })
+        .collect()
+}
+
+/// Timestamps are only rewritten once they are this stale, so `list` and a busy
+/// `watch` do not rewrite the config every time they look at a running tunnel.
+const TIMESTAMP_RESOLUTION_SECS: u64 = 60;
+
+/// Set the timestamp `field` selects to `now` on the forwards in `ids` unless it
+/// is fresher than [`TIMESTAMP_RESOLUTION_SECS`], returning whether any changed.
+fn stamp(
+    config: &mut Config,
+    ids: &[String],
+    now: u64,
+    field: impl Fn(&mut PortForward) -> &mut Option<u64>,
+) -> bool {
+    let mut changed = false;
+    for id in ids {
+        if let Some(forward) = config.forwards_mut().get_mut(id) {
+            let stamp = field(forward);
+            if stamp.is_none_or(|last| now.saturating_sub(last) >= TIMESTAMP_RESOLUTION_SECS) {
+                *stamp = Some(now);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Stamp the forwards in `ids` as having had clients at `now`, returning whether
+/// any timestamp changed. The caller decides whether to save the config.
+pub fn record_active(config: &mut Config, ids: &[String], now: u64) -> bool {
+    stamp(config, ids, now, |forward| &mut forward.last_active_at)
+}
+
+/// Most threads [`running_states`] uses at once.
+const LIVENESS_THREADS: usize = 32;
+
+/// Whether each of `forwards` is running, in the same order. Every check may
+/// spawn a process, so they are spread across threads instead of run one by one.
+pub fn running_states(forwards: &[&PortForward]) -> Vec<bool> {
+    if forwards.is_empty() {
+        return Vec::new();
+    }
+    let chunk_size = forwards.len().div_ceil(LIVENESS_THREADS);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = forwards
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|f| f.is_running()).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// Stamp the forwards in `ids` as seen running at `now`, returning whether any
+/// timestamp changed. The caller decides whether to save the config.
+pub fn record_seen(config: &mut Config, ids: &[String], now: u64) -> bool {
+    stamp(config, ids, now, |forward| &mut forward.last_seen_at)
+}
+
+/// Ids of forwards whose recorded pid no longer runs their tunnel.
+/// Forwards without a pid are not considered dead.
+pub fn find_dead_forwards(config: &Config) -> Vec<String> {
+    let with_pid: Vec<&PortForward> = config
+        .get_sorted_forwards()
+        .into_iter()
+        .filter(|f| f.pid.is_some())
+        .collect();
+    let states = running_states(&with_pid);
+    with_pid
+        .into_iter()
+        .zip(states)
+        .filter(|&(_, running)| !running)
+        .map(|(f, _)| f.id.clone())
+        .collect()
+}
+
+/// Ids of forwards that were never started or were stopped (no pid) and whose
+/// local port is free, so nothing can be serving them. With `all_stopped`, every
+/// forward without a pid qualifies regardless of its port.
+pub fn find_inactive_forwards(config: &Config, all_stopped: bool) -> Vec<String> {
+    config
+        .get_sorted_forwards()
+        .into_iter()
+        .filter(|f| f.pid.is_none() && (all_stopped || port::is_port_available(&f.bind_addr, f.local_port)))
+        .map(|f| f.id.clone())
+        .collect()
+}
+
+/// Remove the forwards reported by [`find_dead_forwards`].
+/// The caller is responsible for saving the config.
+pub fn cleanup_dead_forwards(config: &mut Config) -> CleanupReport {
+    let removed = find_dead_forwards(config)
+        .iter()
+        .filter_map(|id| config.remove_forward(id))
+        .collect::<Vec<_>>();
+    close_unused_masters(config, &removed);
+
+    CleanupReport { removed }
+}
+
+/// Mark a forward as enabled or disabled, returning false if it already was.
+/// A running tunnel is left alone; disabling only affects bulk operations.
+pub fn set_enabled(config: &mut Config, id: &str, enabled: bool) -> Result<bool> {
+    let forward = config.forwards_mut().get_mut(id)
+        .with_context(|| format!("Forward not found: {}", id))?;
+    let changed = forward.enabled != enabled;
+    forward.enabled = enabled;
+    Ok(changed)
+}
+
+/// Ids of enabled forwards that are not running. Only forwards with a recorded
+/// pid count unless `include_stopped`, which adds registered or stopped ones.
+fn find_relaunchable_forwards(config: &Config, include_stopped: bool) -> Vec<String> {
+    let candidates: Vec<&PortForward> = config
+        .get_sorted_forwards()
+        .into_iter()
+        .filter(|f| f.enabled && (include_stopped || f.pid.is_some()))
+        .collect();
+    let states = running_states(&candidates);
+    candidates
+        .into_iter()
+        .zip(states)
+        .filter(|&(_, running)| !running)
+        .map(|(f, _)| f.id.clone())
+        .collect()
+}
+
+fn relaunch_all(config: &mut Config, ids: Vec<String>, timeout: Duration, out: &OutputConfig) -> RestartReport {
+    let mut report = RestartReport::default();
+    for id in ids {
+        match relaunch_forward(config, &id, timeout, out) {
+            Ok(pid) => report.revived.push((id, pid)),
+            Err(e) => report.failed.push((id, format!("{:#}", e))),
+        }
+    }
+    report
+}
+
+/// Relaunch every enabled forward whose recorded pid is no longer running.
+/// Forwards that were never started or were stopped are left alone.
+/// The caller is responsible for saving the config.
+pub fn restart_dead_forwards(
+    config: &mut Config,
+    timeout: Duration,
+    out: &OutputConfig,
+) -> RestartReport {
+    let ids = find_relaunchable_forwards(config, false);
+    relaunch_all(config, ids, timeout, out)
+}
+
+/// Start every enabled forward that is not running, including ones that were
+/// registered, imported, or stopped. The caller is responsible for saving the config.
+pub fn start_stopped_forwards(
+    config: &mut Config,
+    timeout: Duration,
+    out: &OutputConfig,
+) -> RestartReport {
+    let ids = find_relaunchable_forwards(config, true);
+    relaunch_all(config, ids, timeout, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUIET: OutputConfig = OutputConfig { quiet: true };
+
+    /// A pid far above any real pid_max, so it is never running.
+    const DEAD_PID: u32 = 999_999_999;
+
+    fn forward(host: &str, local: u16, pid: Option<u32>) -> PortForward {
+        PortForward {
+            id: forward_id(host, local, 80),
+            host: host.to_string(),
+            user: None,
+            local_port: local,
+            remote_port: 80,
+            requested_local_port: None,
+            remote_host: None,
+            pid,
+            ssh_port: None,
+            connect_timeout: None,
+            ssh_command: None,
+            tags: Vec::new(),
+            ssh_options: Vec::new(),
+            enabled: true,
+            bind_addr: DEFAULT_BIND_ADDR.to_string(),
+            gateway: false,
+            multiplex: false,
+            log_enabled: true,
+            comment: None,
+            log_file: None,
+            created_at: None,
+            last_seen_at: None,
+            last_active_at: None,
+        }
+    }
+
+    fn config_with(forwards: Vec<PortForward>) -> Config {
+        let mut config = Config::new();
+        for f in forwards {
+            config.add_forward(f);
+        }
+        config
+    }
+
+    #[test]
+    fn parse_ports_accepts_one_to_three_parts() {
+        let spec = |local, remote_host: Option<&str>, remote| PortSpec {
+            local,
+            remote,
+            remote_host: remote_host.map(str::to_string),
+        };
+        let valid = [
+            ("3000", spec(3000, None, 3000)),
+            ("8080:80", spec(8080, None, 80)),
+            ("8080:db.internal:5432", spec(8080, Some("db.internal"), 5432)),
+            ("8080:localhost:80", spec(8080, Some("localhost"), 80)),
+        ];
+        for (input, expected) in valid {
+            assert_eq!(parse_ports(input).unwrap(), expected, "{}", input);
+        }
+
+        let invalid = [
+            ("http", "Invalid local port 'http' in 'http'"),
+            ("", "Invalid local port '' in ''"),
+            ("70000", "Invalid local port '70000'"),
+            ("8080:x", "Invalid remote port 'x' in '8080:x'"),
+            ("x:80", "Invalid local port 'x'"),
+            ("1::3", "Remote host must not be empty"),
+            ("8080:db:y", "Invalid remote port 'y'"),
+            ("1:a:b:3", "Invalid ports '1:a:b:3': 4 parts, but at most 3 are allowed"),
+            ("1:2:3:4:5", "5 parts"),
+        ];
+        for (input, message) in invalid {
+            let err = parse_ports(input).unwrap_err();
+            assert!(err.to_string().contains(message), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn validate_host_rejects_malformed_targets() {
+        assert!(validate_host("db").is_ok());
+        assert!(validate_host("user@db.example.com").is_ok());
+        assert!(validate_host("").is_err());
+        assert!(validate_host("my host").is_err());
+        assert!(validate_host("@db").is_err());
+        assert!(validate_host("user@").is_err());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=4).map(|n| backoff_delay(n).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8]);
+        assert_eq!(backoff_delay(20), MAX_BACKOFF);
+        assert_eq!(backoff_delay(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn identical_mappings_get_distinct_ids() {
+        let mut config = config_with(vec![forward("h", 1000, None)]);
+
+        let new = NewForward { log: true, ..NewForward::new("h", 1000, 80) };
+        let second = stored_forward(&config, new.clone(), 1000).unwrap();
+        config.add_forward(second.clone());
+        let third = stored_forward(&config, new.clone(), 1000).unwrap();
+
+        assert_eq!(second.id, "h_1000_80-2");
+        assert_eq!(third.id, "h_1000_80-3");
+        assert_eq!(config.forwards().len(), 2);
+
+        let explicit = NewForward { id: Some("h_1000_80".to_string()), ..new };
+        assert!(stored_forward(&config, explicit, 1000).is_err());
+    }
+
+    #[test]
+    fn inactive_forwards_need_no_pid_and_a_free_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let busy = listener.local_addr().unwrap().port();
+        let free = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let config = config_with(vec![
+            forward("busy", busy, None),
+            forward("free", free, None),
+            forward("dead", free, Some(DEAD_PID)),
+        ]);
+
+        let (busy_id, free_id) = (forward_id("busy", busy, 80), forward_id("free", free, 80));
+        assert_eq!(find_inactive_forwards(&config, false), [free_id.as_str()]);
+        assert_eq!(find_inactive_forwards(&config, true), [busy_id.as_str(), free_id.as_str()]);
+    }
+
+    #[test]
+    fn parse_duration_understands_units() {
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(604_800));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+    }
+
+    #[test]
+    fn parse_seconds_rejects_negative_and_overflowing_values() {
+        assert_eq!(parse_seconds("0.5"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_seconds("0"), Ok(Duration::ZERO));
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("NaN").is_err());
+        assert!(parse_seconds("inf").is_err());
+        assert!(parse_seconds("1e30").is_err());
+        assert!(parse_seconds("soon").is_err());
+    }
+
+    #[test]
+    fn forwards_older_than_skips_recent_and_undated() {
+        let mut old = forward("old", 1000, None);
+        old.created_at = Some(unix_now() - 10 * 86_400);
+        let mut recent = forward("recent", 2000, None);
+        recent.created_at = Some(unix_now());
+        let config = config_with(vec![old, recent, forward("undated", 3000, None)]);
+
+        let ids = forwards_older_than(&config, Duration::from_secs(7 * 86_400), false);
+
+        assert_eq!(ids, ["old_1000_80"]);
+    }
+
+    #[test]
+    fn delete_by_index_and_id_reports_each_outcome() {
+        let mut config = config_with(vec![
+            forward("a", 1000, None),
+            forward("b", 2000, None),
+            forward("c", 3000, None),
+        ]);
+
+        let ids = vec!["0".to_string(), "c_3000_80".to_string(), "7".to_string(), "nope".to_string()];
+        let report = delete_forwards(&mut config, &ids, &[]);
+
+        let deleted: Vec<&str> = report.deleted.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(deleted, ["a_1000_80", "c_3000_80"]);
+        assert_eq!(report.errors, ["Invalid index: 7", "Not found: nope"]);
+        assert_eq!(config.forwards().len(), 1);
+        assert!(config.forwards().contains_key("b_2000_80"));
+    }
+
+    #[test]
+    fn delete_by_tag_only_removes_tagged() {
+        let mut tagged = forward("a", 1000, None);
+        tagged.tags = vec!["work".to_string()];
+        let mut config = config_with(vec![tagged, forward("b", 2000, None)]);
+
+        let report = delete_forwards(&mut config, &[], &["work".to_string()]);
+
+        assert_eq!(report.deleted.len(), 1);
+        assert_eq!(report.deleted[0].id, "a_1000_80");
+        assert!(report.errors.is_empty());
+        assert_eq!(config.forwards().len(), 1);
+    }
+
+    #[test]
+    fn delete_all_empties_config() {
+        let mut config = config_with(vec![forward("a", 1000, None), forward("b", 2000, None)]);
+
+        let report = delete_forwards(&mut config, &["all".to_string()], &[]);
+
+        assert_eq!(report.deleted.len(), 2);
+        assert!(config.forwards().is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn delete_reports_how_each_tunnel_was_stopped() {
+        let mut alive = forward("alive", 1000, None);
+        let mut tunnel = fake_tunnel(&alive);
+        let pid = tunnel.id();
+        alive.pid = Some(pid);
+        let mut config = config_with(vec![alive, forward("stopped", 2000, None)]);
+        // Reap the tunnel as soon as it exits, so it is not left a zombie that still looks alive.
+        let reaper = std::thread::spawn(move || tunnel.wait());
+
+        let report = delete_forwards(&mut config, &["all".to_string()], &[]);
+        let _ = reaper.join();
+
+        assert_eq!(report.deleted.len(), 2);
+        assert_eq!(report.stopped, [("alive_1000_80".to_string(), pid, port::Termination::Terminated)]);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn delete_reports_a_reused_pid_instead_of_killing_it() {
+        // The test process is alive but is not an ssh tunnel for this forward.
+        let reused = forward("a", 1000, Some(std::process::id()));
+        let mut config = config_with(vec![reused]);
+
+        let report = delete_forwards(&mut config, &["a_1000_80".to_string()], &[]);
+
+        assert_eq!(report.deleted.len(), 1);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.warnings, [format!(
+            "a_1000_80: pid {} no longer runs this tunnel; leaving it alone", std::process::id()
+        )]);
+    }
+
+    #[test]
+    fn edit_regenerates_id_and_suffixes_collisions() {
+        let mut config = config_with(vec![forward("a", 1000, None), forward("b", 2000, None)]);
+
+        let edit = ForwardEdit { remote_port: Some(81), ..Default::default() };
+        let edited = edit_forward(&mut config, "a_1000_80", edit, Duration::ZERO, &QUIET).unwrap();
+
+        assert_eq!(edited.forward.id, "a_1000_81");
+        assert!(!edited.restarted);
+        assert!(!config.forwards().contains_key("a_1000_80"));
+        assert!(config.forwards().contains_key("a_1000_81"));
+
+        let edit = ForwardEdit {
+            host: Some("b".to_string()),
+            local_port: Some(2000),
+            remote_port: Some(80),
+            ..Default::default()
+        };
+        let edited = edit_forward(&mut config, "a_1000_81", edit, Duration::ZERO, &QUIET).unwrap();
+        assert_eq!(edited.forward.id, "b_2000_80-2");
+        assert_eq!(config.forwards().len(), 2);
+    }
+
+    #[test]
+    fn rename_moves_the_key_and_rejects_collisions() {
+        let mut config = config_with(vec![forward("a", 1000, Some(DEAD_PID)), forward("b", 2000, None)]);
+
+        let renamed = rename_forward(&mut config, "a_1000_80", "web").unwrap();
+        assert_eq!(renamed.id, "web");
+        assert_eq!(renamed.pid, Some(DEAD_PID));
+        assert!(!config.forwards().contains_key("a_1000_80"));
+        assert_eq!(config.forwards()["web"].id, "web");
+
+        let error = rename_forward(&mut config, "web", "b_2000_80").unwrap_err();
+        assert!(error.to_string().contains("already exists"), "{}", error);
+        assert!(rename_forward(&mut config, "web", "web").is_err());
+        assert!(rename_forward(&mut config, "web", "42").is_err());
+        assert!(rename_forward(&mut config, "missing", "other").is_err());
+        assert_eq!(config.forwards().len(), 2);
+        assert!(config.forwards().contains_key("web"));
+        assert!(config.forwards().contains_key("b_2000_80"));
+    }
+
+    #[test]
+    fn move_creates_the_target_profile_and_rejects_id_clashes() {
+        let mut config = config_with(vec![forward("a", 1000, Some(DEAD_PID)), forward("b", 2000, None)]);
+
+        let moved = move_forward(&mut config, "a_1000_80", "work").unwrap();
+        assert_eq!(moved.pid, Some(DEAD_PID));
+        assert!(!config.forwards().contains_key("a_1000_80"));
+        assert!(config.profiles["work"].contains_key("a_1000_80"));
+
+        config.profiles.get_mut("work").unwrap().insert("b_2000_80".to_string(), forward("b", 2000, None));
+        let error = move_forward(&mut config, "b_2000_80", "work").unwrap_err();
+        assert!(error.to_string().contains("already has a forward"), "{}", error);
+        assert!(config.forwards().contains_key("b_2000_80"));
+        assert!(move_forward(&mut config, "b_2000_80", crate::config::DEFAULT_PROFILE).is_err());
+        assert!(move_forward(&mut config, "missing", "work").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit_keeps_the_original_forward_when_the_new_target_is_unreachable() {
+        let mut stopped = forward("unreachable-a.invalid", 1000, None);
+        stopped.log_enabled = false;
+        let mut config = config_with(vec![stopped]);
+        config.settings.retry.attempts = 0;
+        // A stand-in tunnel whose command line looks like this forward's ssh
+        let mut tunnel = std::process::Command::new("bash")
+            .args(["-c", r#"exec -a ssh sh -c "sleep 10; :" x -N -L 1000:localhost:80 unreachable-a.invalid"#])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let id = "unreachable-a.invalid_1000_80";
+        config.forwards_mut().get_mut(id).unwrap().pid = Some(tunnel.id());
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(config.forwards()[id].is_running());
+
+        let edit = ForwardEdit { host: Some("unreachable-b.invalid".to_string()), ..Default::default() };
+        let err = edit_forward(&mut config, id, edit, Duration::from_secs(1), &QUIET).unwrap_err();
+        tunnel.wait().unwrap();
+
+        assert!(format!("{:#}", err).contains("the edit was not applied"), "{:#}", err);
+        assert_eq!(config.forwards().len(), 1);
+        let kept = &config.forwards()[id];
+        assert_eq!(kept.host, "unreachable-a.invalid");
+        assert!(!kept.is_running());
+    }
+
+    #[test]
+    fn timestamps_are_only_rewritten_once_stale() {
+        let mut config = config_with(vec![forward("a", 1000, None), forward("b", 2000, None)]);
+        let ids = ["a_1000_80".to_string()];
+
+        assert!(record_seen(&mut config, &ids, 100));
+        assert!(!record_seen(&mut config, &ids, 100));
+        assert!(!record_seen(&mut config, &ids, 159));
+        assert!(record_seen(&mut config, &ids, 160));
+        assert!(!record_seen(&mut config, &[], 300));
+        assert_eq!(config.forwards()["a_1000_80"].last_seen_at, Some(160));
+        assert!(config.forwards()["b_2000_80"].last_seen_at.is_none());
+
+        // Each timestamp goes stale on its own
+        assert!(config.forwards()["a_1000_80"].last_active_at.is_none());
+        assert!(record_active(&mut config, &ids, 170));
+        assert_eq!(config.forwards()["a_1000_80"].last_active_at, Some(170));
+        assert_eq!(config.forwards()["a_1000_80"].last_seen_at, Some(160));
+    }
+
+    #[test]
+    fn edit_rejects_malformed_hosts_and_keeps_the_forward() {
+        let mut config = config_with(vec![forward("a", 1000, None)]);
+
+        for host in ["", "a b", "user@"] {
+            let edit = ForwardEdit { host: Some(host.to_string()), ..Default::default() };
+            assert!(edit_forward(&mut config, "a_1000_80", edit, Duration::ZERO, &QUIET).is_err(), "{:?}", host);
+        }
+        assert_eq!(config.forwards()["a_1000_80"].host, "a");
+    }
+
+    #[test]
+    fn edit_toggles_logging() {
+        let mut config = config_with(vec![forward("a", 1000, None)]);
+
+        let edit = ForwardEdit { log: Some(false), ..Default::default() };
+        let edited = edit_forward(&mut config, "a_1000_80", edit, Duration::ZERO, &QUIET).unwrap();
+        assert!(!edited.forward.log_enabled);
+        assert!(edited.forward.log_file.is_none());
+
+        let edit = ForwardEdit { log: Some(true), ..Default::default() };
+        let edited = edit_forward(&mut config, "a_1000_80", edit, Duration::ZERO, &QUIET).unwrap();
+        assert!(edited.forward.log_enabled);
+        assert!(edited.forward.log_file.is_some());
+    }
+
+    #[test]
+    fn import_merge_skips_colliding_ids_and_clears_pids() {
+        let mut config = config_with(vec![forward("a", 1000, Some(DEAD_PID))]);
+        let imported = config_with(vec![forward("a", 1000, None), forward("b", 2000, Some(DEAD_PID))]);
+
+        let report = import_forwards(&mut config, imported, ImportMode::Merge);
+
+        assert_eq!(report.imported, [("default".to_string(), "b_2000_80".to_string())]);
+        assert_eq!(report.skipped, [("default".to_string(), "a_1000_80".to_string())]);
+        assert_eq!(config.forwards()["a_1000_80"].pid, Some(DEAD_PID));
+        assert_eq!(config.forwards()["b_2000_80"].pid, None);
+    }
+
+    #[test]
+    fn import_replace_drops_existing() {
+        let mut config = config_with(vec![forward("a", 1000, None)]);
+        let imported = config_with(vec![forward("b", 2000, None)]);
+
+        let report = import_forwards(&mut config, imported, ImportMode::Replace);
+
+        assert!(report.skipped.is_empty());
+        assert_eq!(config.forwards().len(), 1);
+        assert!(config.forwards().contains_key("b_2000_80"));
+    }
+
+    /// Spawn a process whose command line looks like the tunnel for `forward`:
+    /// `/bin/sh` invoked through a symlink named `ssh`.
+    #[cfg(unix)]
+    fn fake_tunnel(forward: &PortForward) -> std::process::Child {
+        let dir = std::env::temp_dir().join(format!("pfm-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ssh = dir.join("ssh");
+        let _ = std::os::unix::fs::symlink("/bin/sh", &ssh);
+        std::process::Command::new(ssh)
+            .args(["-c", "sleep 5"])
+            .args(SshTunnel::args(forward))
+            .spawn()
+            .unwrap()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn cleanup_removes_dead_and_reused_pids() {
+        let mut alive = forward("alive", 2000, None);
+        let mut tunnel = fake_tunnel(&alive);
+        alive.pid = Some(tunnel.id());
+        // The test binary is running, but it is not this forward's tunnel.
+        let reused = forward("reused", 4000, Some(std::process::id()));
+        let mut config = config_with(vec![
+            forward("dead", 1000, Some(DEAD_PID)),
+            alive,
+            forward("nopid", 3000, None),
+            reused,
+        ]);
+
+        let report = cleanup_dead_forwards(&mut config);
+        let _ = tunnel.kill();
+        let _ = tunnel.wait();
+
+        let removed: Vec<&str> = report.removed.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(removed, ["dead_1000_80", "reused_4000_80"]);
+        assert_eq!(config.forwards().len(), 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn stop_forward_kills_the_tunnel_and_keeps_the_forward() {
+        let mut alive = forward("alive", 2000, None);
+        let mut tunnel = fake_tunnel(&alive);
+        alive.pid = Some(tunnel.id());
+        let mut config = config_with(vec![alive, forward("dead", 1000, Some(DEAD_PID))]);
+
+        assert!(stop_forward(&mut config, "alive_2000_80", &QUIET).unwrap());
+        let status = tunnel.wait().unwrap();
+        assert!(!stop_forward(&mut config, "dead_1000_80", &QUIET).unwrap());
+
+        assert!(!status.success());
+        assert!(config.forwards().values().all(|f| f.pid.is_none()));
+        assert_eq!(config.forwards().len(), 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn running_states_keeps_order_across_many_forwards() {
+        let mut alive = forward("alive", 1, None);
+        let mut tunnel = fake_tunnel(&alive);
+        alive.pid = Some(tunnel.id());
+        let mut forwards: Vec<PortForward> = (100..400)
+            .map(|port| forward("dead", port, Some(DEAD_PID)))
+            .collect();
+        forwards.insert(150, alive);
+
+        let refs: Vec<&PortForward> = forwards.iter().collect();
+        let states = running_states(&refs);
+        let _ = tunnel.kill();
+        let _ = tunnel.wait();
+
+        assert_eq!(states.len(), 301);
+        let running: Vec<usize> = states.iter()
+            .enumerate()
+            .filter(|&(_, &running)| running)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(running, [150]);
+    }
+
+    #[test]
+    fn orphans_skip_recorded_pids_in_any_profile_and_multiplex_masters() {
+        let live = forward("h", 1000, Some(100));
+        let mut config = config_with(vec![live.clone()]);
+        let home = config.active_profile().to_string();
+        config.use_profile("other");
+        config.add_forward(PortForward { pid: None, ..live });
+        config.use_profile(&home);
+
+        let tunnel = "ssh -N -L 1000:localhost:80 h";
+        let master = "ssh -o ControlMaster=auto -o ControlPersist=10m -N -L 1000:localhost:80 h";
+        let processes = vec![
+            (100, tunnel.to_string()),
+            (200, master.to_string()),
+            (300, tunnel.to_string()),
+        ];
+
+        assert_eq!(orphans_among(&config, processes), [(300, "h_1000_80".to_string())]);
+    }
+
+    #[test]
+    fn restart_dead_skips_forwards_without_a_pid() {
+        let dead = forward("dead", 1, Some(DEAD_PID));
+        let registered = forward("registered", 2, None);
+        let disabled = PortForward { enabled: false, ..forward("disabled", 3, Some(DEAD_PID)) };
+        let (dead_id, registered_id) = (dead.id.clone(), registered.id.clone());
+        let config = config_with(vec![dead, registered, disabled]);
+
+        assert_eq!(find_relaunchable_forwards(&config, false), [dead_id.as_str()]);
+        assert_eq!(find_relaunchable_forwards(&config, true), [dead_id, registered_id]);
+    }
+
+    #[test]
+    fn masters_close_only_when_no_multiplexed_forward_remains() {
+        let multiplexed = |host: &str, local: u16| PortForward { multiplex: true, ..forward(host, local, None) };
+        let config = config_with(vec![multiplexed("shared", 1), forward("plain", 2, None)]);
+        let removed = vec![
+            multiplexed("shared", 3),
+            multiplexed("plain", 4),
+            multiplexed("plain", 5),
+            forward("alone", 6, None),
+        ];
+
+        let hosts: Vec<&str> = unused_masters(&config, &removed).iter().map(|f| f.host.as_str()).collect();
+
+        assert_eq!(hosts, ["plain"]);
+    }
+}
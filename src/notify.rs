@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use crate::config::PortForward;
+use crate::port;
+
+/// Where to send an alert when a watched tunnel dies.
+#[derive(Debug, Clone)]
+pub enum Notifier {
+    /// A desktop notification; needs pfm built with the `notify` feature
+    Desktop,
+    /// A JSON `POST` to this URL, sent with `curl`, which must be on `$PATH`
+    Webhook(String),
+}
+
+impl Notifier {
+    /// Whether this build can show desktop notifications.
+    pub const DESKTOP_AVAILABLE: bool = cfg!(feature = "notify");
+
+    pub fn tunnel_died(&self, profile: &str, forward: &PortForward) -> Result<()> {
+        match self {
+            Notifier::Desktop => {
+                let title = "pfm: tunnel down";
+                let body = format!(
+                    "{} (localhost:{} → {}:{})",
+                    forward.id, forward.local_port, forward.target(), forward.remote_port
+                );
+                desktop(title, &body)
+            }
+            Notifier::Webhook(url) => {
+                let payload = json!({
+                    "event": "tunnel_died",
+                    "profile": profile,
+                    "id": forward.id,
+                    "host": forward.host,
+                    "user": forward.user,
+                    "local_port": forward.local_port,
+                    "remote_port": forward.remote_port,
+                    "pid": forward.pid,
+                });
+                post_json(url, &payload.to_string())
+            }
+        }
+    }
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "notify")]
+fn desktop(title: &str, body: &str) -> Result<()> {
+    notify_rust::Notification::new()
+        .summary(title)
+        .body(body)
+        .show()
+        .context("Failed to show desktop notification")?;
+    Ok(())
+}
+
+#[cfg(not(feature = "notify"))]
+fn desktop(_title: &str, _body: &str) -> Result<()> {
+    anyhow::bail!("Desktop notifications need pfm built with the `notify` feature")
+}
+
+/// Locate the `curl` binary webhooks are sent with.
+pub fn find_curl_binary() -> Option<PathBuf> {
+    port::find_binary("curl")
+}
+
+fn post_json(url: &str, payload: &str) -> Result<()> {
+    run(Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "10"])
+        .args(["-H", "Content-Type: application/json"])
+        .args(["--data", payload])
+        .arg(url))
+        .with_context(|| format!("Failed to POST to {}", url))
+}
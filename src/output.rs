@@ -0,0 +1,35 @@
+/// How the CLI should present its output, chosen once from the command line
+/// and passed to every command and library function that prints.
+/// New presentation options belong here so every command and macro sees them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputConfig {
+    /// Suppress informational output and warnings; errors are still printed
+    pub quiet: bool,
+}
+
+/// Print decorative/informational output unless `$out` (an [`OutputConfig`]) is quiet.
+#[macro_export]
+macro_rules! info {
+    ($out:expr) => {
+        if !$out.quiet {
+            println!();
+        }
+    };
+    ($out:expr, $($arg:tt)*) => {
+        if !$out.quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Print a non-fatal warning to stderr unless `$out` (an [`OutputConfig`]) is quiet.
+#[macro_export]
+macro_rules! warning {
+    ($out:expr, $($arg:tt)*) => {
+        if !$out.quiet {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub use crate::{info, warning};
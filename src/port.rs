@@ -1,19 +1,171 @@
-use std::net::TcpListener;
-const MAX_PORT: u16 = 65535;
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpListener};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-pub fn is_port_available(port:u16) -> bool {
-    TcpListener::bind(("127.0.0.1", port)).is_ok()
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::DEFAULT_BIND_ADDR;
+use crate::output::{info, OutputConfig};
+
+/// Whether `port` is free on `bind_addr`, the address the forward's local end
+/// listens on. For the default bind address ssh listens on both `127.0.0.1`
+/// and `::1`, so both families are checked.
+pub fn is_port_available(bind_addr: &str, port: u16) -> bool {
+    port_status(bind_addr, port) == PortStatus::Available
+}
+
+/// [`is_port_available`] on the default bind address.
+pub fn is_port_available_ipv4(port: u16) -> bool {
+    is_port_available(DEFAULT_BIND_ADDR, port)
 }
 
-pub fn find_available_port(start_port: u16) -> Option<u16> {
-    for port in start_port..MAX_PORT {
-        if is_port_available(port) {
-            return Some(port)
+/// Whether a local port can be bound, and if not, why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortStatus {
+    Available,
+    InUse,
+    /// Binding needs more privileges, e.g. a port below 1024 without root
+    PermissionDenied,
+}
+
+/// Like [`is_port_available`], but tells a busy port apart from one this
+/// process isn't allowed to bind.
+pub fn port_status(bind_addr: &str, port: u16) -> PortStatus {
+    let addrs = match bind_addr {
+        DEFAULT_BIND_ADDR | "localhost" => {
+            vec![IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)]
         }
+        "*" | "" => vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED), IpAddr::V6(Ipv6Addr::UNSPECIFIED)],
+        _ => match parse_bind_addr(bind_addr) {
+            Ok(addr) => vec![addr],
+            Err(_) => return socket_status(TcpListener::bind((bind_addr, port))),
+        },
+    };
+    addrs.into_iter()
+        .map(|addr| bind_status(addr, port))
+        .find(|&status| status != PortStatus::Available)
+        .unwrap_or(PortStatus::Available)
+}
+
+fn bind_status(addr: IpAddr, port: u16) -> PortStatus {
+    socket_status(TcpListener::bind((addr, port)))
+}
+
+fn socket_status(bound: std::io::Result<TcpListener>) -> PortStatus {
+    match bound {
+        Ok(_) => PortStatus::Available,
+        // An address family the host does not support (e.g. IPv6 disabled); ssh skips it too
+        Err(e) if e.kind() == ErrorKind::AddrNotAvailable => PortStatus::Available,
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => PortStatus::PermissionDenied,
+        Err(_) => PortStatus::InUse,
+    }
+}
+
+/// Parse an IP address given as a bind address, with or without the
+/// brackets ssh uses around IPv6 addresses (`[::1]`).
+pub fn parse_bind_addr(value: &str) -> Result<IpAddr, String> {
+    let unbracketed = value.strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .unwrap_or(value);
+    unbracketed.parse()
+        .map_err(|_| format!("'{}' is not an IP address, 'localhost' or '*'", value))
+}
+
+/// The address to connect to when checking that a tunnel listening on
+/// `bind_addr` is up: loopback for the default and wildcard addresses.
+pub fn connect_addr(bind_addr: &str) -> IpAddr {
+    match parse_bind_addr(bind_addr) {
+        Ok(IpAddr::V6(addr)) if addr.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        Ok(addr) if !addr.is_unspecified() => addr,
+        _ => IpAddr::V4(Ipv4Addr::LOCALHOST),
+    }
+}
+
+/// Ports below this can only be bound by root on Linux.
+pub const FIRST_UNPRIVILEGED_PORT: u16 = 1024;
+
+/// Whether binding `port` locally needs elevated privileges on Linux.
+/// Port 0 (let the OS choose) never does.
+pub fn requires_privilege(port: u16) -> bool {
+    port != 0 && port < FIRST_UNPRIVILEGED_PORT
+}
+
+/// Whether this process can bind privileged ports, i.e. runs as root.
+#[cfg(unix)]
+pub fn has_privilege() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Windows doesn't restrict low ports.
+#[cfg(not(unix))]
+pub fn has_privilege() -> bool {
+    true
+}
+
+/// Inclusive range of local ports that automatic port selection may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    /// Number of ports in the range.
+    pub fn count(&self) -> u32 {
+        u32::from(self.end) - u32::from(self.start) + 1
+    }
+
+    /// First port in the range that is free on `bind_addr` and not in `taken`,
+    /// or an error naming the range.
+    pub fn find_available(&self, bind_addr: &str, taken: &[u16]) -> Result<u16> {
+        find_available_port_in_range(bind_addr, self.start, self.end, taken).with_context(|| {
+            format!("No available port in {} (tried {} port(s))", self, self.count())
+        })
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Parse `START-END`, e.g. `20000-20999`.
+pub fn parse_port_range(value: &str) -> Result<PortRange, String> {
+    let (start, end) = value.split_once('-')
+        .ok_or_else(|| format!("'{}' is not a range like 20000-20999", value))?;
+    let parse = |port: &str| match port.trim().parse::<u16>() {
+        Ok(0) | Err(_) => Err(format!("'{}' is not a port between 1 and 65535", port.trim())),
+        Ok(port) => Ok(port),
+    };
+    let range = PortRange { start: parse(start)?, end: parse(end)? };
+    if range.start > range.end {
+        return Err(format!("range {} ends before it starts", range));
     }
-    None
+    Ok(range)
+}
+
+/// The first port in `start..=end` that is free on `bind_addr`, if any. Ports in
+/// `taken` are skipped even when nothing listens on them, e.g. those of stopped forwards.
+pub fn find_available_port_in_range(bind_addr: &str, start: u16, end: u16, taken: &[u16]) -> Option<u16> {
+    (start..=end).find(|&port| !taken.contains(&port) && is_port_available(bind_addr, port))
 }
 
+/// Find the first port at or above `start_port` that is free on `bind_addr`
+/// and not in `taken`, trying at most `max_attempts` ports.
+pub fn find_available_port(bind_addr: &str, start_port: u16, max_attempts: u16, taken: &[u16]) -> Result<u16> {
+    let end = start_port.saturating_add(max_attempts.saturating_sub(1));
+    if max_attempts == 0 {
+        anyhow::bail!("No available port: port search is disabled (0 attempts)");
+    }
+    PortRange { start: start_port, end }.find_available(bind_addr, taken)
+}
+
+#[cfg(not(windows))]
 pub fn is_process_running(pid: u32) -> bool {
     std::process::Command::new("kill")
         .arg("-0")
@@ -22,3 +174,332 @@ pub fn is_process_running(pid: u32) -> bool {
         .map(|output| output.status.success())
         .unwrap_or(false)
 }
+
+/// A process handle can still be opened for a while after the process exits,
+/// so also check that it has no exit code yet.
+#[cfg(windows)]
+pub fn is_process_running(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, ERROR_ACCESS_DENIED, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    // SAFETY: the handle is only used after the null check and is closed before returning.
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            // Another user's process exists even though we may not query it
+            return GetLastError() == ERROR_ACCESS_DENIED;
+        }
+        let mut exit_code = 0;
+        let queried = GetExitCodeProcess(handle, &mut exit_code) != 0;
+        CloseHandle(handle);
+        queried && exit_code == STILL_ACTIVE as u32
+    }
+}
+
+/// How long a process gets to exit after being asked to before it is killed outright.
+pub const TERMINATE_GRACE: Duration = Duration::from_secs(2);
+const TERMINATE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How [`terminate`] stopped a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// It had exited before it was signalled
+    AlreadyStopped,
+    /// It exited when asked to (SIGTERM)
+    Terminated,
+    /// It ignored the request and was killed (SIGKILL)
+    Killed,
+}
+
+impl Termination {
+    /// The signal that ended the process, as users of the platform know it.
+    pub fn signal_name(self) -> &'static str {
+        match (self, cfg!(windows)) {
+            (Termination::AlreadyStopped, _) => "none",
+            (Termination::Terminated, false) => "SIGTERM",
+            (Termination::Killed, false) => "SIGKILL",
+            (Termination::Terminated, true) => "taskkill",
+            (Termination::Killed, true) => "taskkill /F",
+        }
+    }
+
+    /// What happened to `pid`, as shown to the user after stopping it.
+    pub fn describe(self, pid: u32) -> String {
+        match self {
+            Termination::AlreadyStopped => format!("Process {} was already stopped", pid),
+            Termination::Terminated => format!("Stopped process: {} ({})", pid, self.signal_name()),
+            Termination::Killed => format!(
+                "Stopped process: {} ({} after it ignored the request to exit for {}s)",
+                pid, self.signal_name(), TERMINATE_GRACE.as_secs()
+            ),
+        }
+    }
+}
+
+/// Ask `pid` to exit, or kill it when `force` is set. Returns false if there
+/// was no such process.
+#[cfg(unix)]
+fn signal(pid: u32, force: bool) -> Result<bool> {
+    let raw_pid = libc::pid_t::try_from(pid)
+        .with_context(|| format!("Invalid process id {}", pid))?;
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    // SAFETY: kill only sends a signal; it has no memory-safety preconditions.
+    if unsafe { libc::kill(raw_pid, signal) } == 0 {
+        return Ok(true);
+    }
+    let error = std::io::Error::last_os_error();
+    if error.raw_os_error() == Some(libc::ESRCH) {
+        return Ok(false);
+    }
+    Err(error).with_context(|| format!("Failed to kill process {}", pid))
+}
+
+#[cfg(windows)]
+fn signal(pid: u32, force: bool) -> Result<bool> {
+    if !is_process_running(pid) {
+        return Ok(false);
+    }
+    let mut command = std::process::Command::new("taskkill");
+    if force {
+        command.arg("/F");
+    }
+    let output = command
+        .args(["/PID", &pid.to_string()])
+        .output()
+        .context("Failed to execute taskkill command")?;
+    if output.status.success() {
+        Ok(true)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to kill process {}:{}", pid, stderr)
+    }
+}
+
+/// Stop `pid` gracefully: ask it to exit, give it `grace` to do so (polling
+/// `has_exited`), and only kill it if it is still alive after that.
+pub fn terminate_with(pid: u32, grace: Duration, mut has_exited: impl FnMut() -> bool) -> Result<Termination> {
+    match signal(pid, false) {
+        Ok(false) => return Ok(Termination::AlreadyStopped),
+        Ok(true) => {
+            let deadline = Instant::now() + grace;
+            loop {
+                if has_exited() {
+                    return Ok(Termination::Terminated);
+                }
+                if Instant::now() >= deadline {
+                    break;
+                }
+                std::thread::sleep(TERMINATE_POLL_INTERVAL);
+            }
+        }
+        // Escalate straight away: e.g. windowless programs such as ssh on
+        // Windows can only be stopped forcefully.
+        Err(_) => {}
+    }
+    if signal(pid, true)? {
+        Ok(Termination::Killed)
+    } else {
+        // It exited between the last check and the kill.
+        Ok(Termination::Terminated)
+    }
+}
+
+/// [`terminate_with`] for a process that is not our child, with the default grace period.
+pub fn terminate(pid: u32) -> Result<Termination> {
+    terminate_with(pid, TERMINATE_GRACE, || !is_process_running(pid))
+}
+
+/// Stop `pid` with [`terminate`] and report which signal it took.
+pub fn kill_process(pid: u32, out: &OutputConfig) -> Result<()> {
+    let termination = terminate(pid)?;
+    info!(out, "  {}", termination.describe(pid));
+    Ok(())
+}
+
+/// Locate the executable `name` on `$PATH`, adding `.exe` on Windows.
+pub fn find_binary(name: &str) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// The command line of a running process, if it can be read.
+pub fn process_command_line(pid: u32) -> Option<String> {
+    if let Ok(raw) = std::fs::read(format!("/proc/{}/cmdline", pid)) {
+        let args: Vec<String> = raw
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect();
+        if !args.is_empty() {
+            return Some(args.join(" "));
+        }
+    }
+    if cfg!(windows) {
+        return None;
+    }
+    let output = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "args="])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let command = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!command.is_empty()).then_some(command)
+}
+
+/// Every process with its command line, as reported by `ps` (empty on Windows).
+pub fn list_processes() -> Vec<(u32, String)> {
+    if cfg!(windows) {
+        return Vec::new();
+    }
+    let Ok(output) = std::process::Command::new("ps").args(["-eo", "pid=,args="]).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, command) = line.trim_start().split_once(char::is_whitespace)?;
+            Some((pid.parse().ok()?, command.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Count established connections whose local end is `port` in a
+/// `/proc/net/tcp`-style table. State `01` is ESTABLISHED.
+fn count_established(table: &str, port: u16) -> usize {
+    let local_suffix = format!(":{:04X}", port);
+    table
+        .lines()
+        .skip(1)
+        .filter(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            fields.len() > 3 && fields[1].ends_with(&local_suffix) && fields[3] == "01"
+        })
+        .count()
+}
+
+/// Established connections accepted on local `port`, i.e. clients currently
+/// using a tunnel. Only available on Linux, where it is read from `/proc/net`.
+pub fn active_connections(port: u16) -> Option<usize> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let mut total = None;
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(contents) = std::fs::read_to_string(table) {
+            *total.get_or_insert(0) += count_established(&contents, port);
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_only_established_connections_on_the_local_port() {
+        let table = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1
+   1: 0100007F:1F90 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 2
+   2: 0100007F:C350 0100007F:1F90 01 00000000:00000000 00:00000000 00000000  1000        0 3
+   3: 0100007F:1F90 0100007F:C351 06 00000000:00000000 00:00000000 00000000  1000        0 4
+";
+
+        assert_eq!(count_established(table, 8080), 1);
+        assert_eq!(count_established(table, 50000), 1);
+        assert_eq!(count_established(table, 22), 0);
+    }
+
+    #[test]
+    fn parses_port_ranges() {
+        assert_eq!(parse_port_range("20000-20999"), Ok(PortRange { start: 20000, end: 20999 }));
+        assert_eq!(parse_port_range("8080-8080").map(|r| r.count()), Ok(1));
+        assert!(parse_port_range("8080").is_err());
+        assert!(parse_port_range("0-10").is_err());
+        assert!(parse_port_range("9000-8000").is_err());
+        assert!(parse_port_range("1-70000").is_err());
+    }
+
+    #[test]
+    fn ports_below_1024_require_privilege() {
+        assert!(requires_privilege(1));
+        assert!(requires_privilege(80));
+        assert!(requires_privilege(1023));
+        assert!(!requires_privilege(0));
+        assert!(!requires_privilege(1024));
+        assert!(!requires_privilege(8080));
+    }
+
+    #[test]
+    fn find_available_port_stops_after_max_attempts() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let busy = listener.local_addr().unwrap().port();
+
+        let err = find_available_port(DEFAULT_BIND_ADDR, busy, 1, &[]).unwrap_err();
+        assert_eq!(port_status(DEFAULT_BIND_ADDR, busy), PortStatus::InUse);
+
+        assert!(err.to_string().contains("tried 1 port(s)"), "{}", err);
+    }
+
+    #[test]
+    fn find_available_port_skips_taken_ports() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let free = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        assert!(find_available_port(DEFAULT_BIND_ADDR, free, 1, &[free]).is_err());
+        assert_eq!(find_available_port_in_range(DEFAULT_BIND_ADDR, free, free, &[]), Some(free));
+    }
+
+    #[test]
+    fn port_bound_on_ipv6_loopback_is_not_available() {
+        let Ok(listener) = TcpListener::bind(("::1", 0)) else {
+            // No IPv6 loopback on this host; nothing to check.
+            return;
+        };
+        let port = listener.local_addr().unwrap().port();
+
+        assert!(!is_port_available_ipv4(port));
+        assert!(!is_port_available("::1", port));
+        assert!(!is_port_available("[::1]", port));
+        assert_eq!(port_status("::1", port), PortStatus::InUse);
+        assert_eq!(find_available_port_in_range("::1", port, port, &[]), None);
+    }
+
+    #[test]
+    fn bind_addresses_parse_with_or_without_brackets() {
+        assert_eq!(parse_bind_addr("::1"), Ok(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(parse_bind_addr("[::1]"), Ok(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(parse_bind_addr("10.0.0.5"), Ok(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5))));
+        assert!(parse_bind_addr("example.com").is_err());
+        assert_eq!(connect_addr("0.0.0.0"), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(connect_addr("::"), IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(connect_addr("*"), IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn terminate_escalates_only_when_the_request_to_exit_is_ignored() {
+        let spawn = |script: &str| std::process::Command::new("sh").args(["-c", script]).spawn().unwrap();
+        let stop = |child: &mut std::process::Child| {
+            let pid = child.id();
+            terminate_with(pid, Duration::from_millis(300), || !matches!(child.try_wait(), Ok(None))).unwrap()
+        };
+
+        let mut polite = spawn("sleep 5");
+        assert_eq!(stop(&mut polite), Termination::Terminated);
+
+        let mut stubborn = spawn("trap '' TERM; sleep 5");
+        // Give the shell time to install the trap before signalling it.
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(stop(&mut stubborn), Termination::Killed);
+        let _ = stubborn.wait();
+
+        // Far above any real pid_max, so it is never running.
+        assert_eq!(terminate(999_999_999).unwrap(), Termination::AlreadyStopped);
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+/// Connect to `addr`, returning how long the handshake took.
+pub fn tcp(addr: SocketAddr, timeout: Duration) -> Result<Duration> {
+    let started = Instant::now();
+    TcpStream::connect_timeout(&addr, timeout)
+        .with_context(|| format!("TCP connect to {} failed", addr))?;
+    Ok(started.elapsed())
+}
+
+/// Issue `GET path` over the forward and return the HTTP status code.
+/// Any 2xx or 3xx response is treated as success.
+pub fn http(addr: SocketAddr, path: &str, timeout: Duration) -> Result<u16> {
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)
+        .with_context(|| format!("TCP connect to {} failed", addr))?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: localhost:{}\r\nConnection: close\r\n\r\n",
+        path, addr.port()
+    );
+    stream.write_all(request.as_bytes())
+        .context("Failed to send HTTP request")?;
+
+    let mut head = [0u8; 64];
+    let read = stream.read(&mut head)
+        .context("Failed to read HTTP response")?;
+    let status_line = String::from_utf8_lossy(&head[..read]);
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .with_context(|| format!("Malformed HTTP response: {:?}", status_line.lines().next().unwrap_or("")))?;
+
+    if !(200..400).contains(&status) {
+        anyhow::bail!("HTTP {}", status);
+    }
+    Ok(status)
+}
+
+/// Round-trip statistics over a series of connects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency {
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+}
+
+impl Latency {
+    /// Summarise `samples`, or `None` if there are none.
+    pub fn from_samples(samples: &[Duration]) -> Option<Latency> {
+        let count = u32::try_from(samples.len()).ok().filter(|&n| n > 0)?;
+        Some(Latency {
+            min: *samples.iter().min()?,
+            avg: samples.iter().sum::<Duration>() / count,
+            max: *samples.iter().max()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn summarises_connect_times() {
+        let ms = Duration::from_millis;
+        assert_eq!(
+            Latency::from_samples(&[ms(3), ms(1), ms(2)]),
+            Some(Latency { min: ms(1), avg: ms(2), max: ms(3) })
+        );
+        assert_eq!(Latency::from_samples(&[]), None);
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(tcp(addr, Duration::from_secs(1)).is_ok());
+        drop(listener);
+        assert!(tcp(addr, Duration::from_secs(1)).is_err());
+    }
+}
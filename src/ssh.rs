@@ -1,44 +1,647 @@
-use std::process::{Command, Child};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::IsTerminal;
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Child, Stdio};
+use std::time::{Duration, Instant};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, PortForward};
+use crate::output::{info, OutputConfig};
+use crate::port;
+
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How often to check whether a freshly spawned ssh has exited.
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Extra time allowed past ssh's ConnectTimeout for the forward to start listening.
+const CONNECT_TIMEOUT_GRACE: Duration = Duration::from_secs(2);
+
+/// How long a shared master connection outlives its last multiplexed forward.
+const CONTROL_PERSIST: &str = "10m";
+
+/// How often to retry an ssh process that exits immediately after spawning.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SshRetryPolicy {
+    /// Retries after the first attempt
+    pub attempts: u8,
+    pub delay_ms: u64,
+    /// Double the delay after every retry, up to [`MAX_RETRY_DELAY`]. Set per
+    /// command (`add --retries`), never stored in the config.
+    #[serde(skip)]
+    pub backoff: bool,
+}
+
+impl Default for SshRetryPolicy {
+    fn default() -> Self {
+        SshRetryPolicy {
+            attempts: 2,
+            delay_ms: 1000,
+            backoff: false,
+        }
+    }
+}
+
+/// Longest wait between backed-off retries; someone is usually waiting on `add`.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+impl SshRetryPolicy {
+    /// How long to wait before retry number `attempt`, counting from 1.
+    pub fn delay(&self, attempt: u8) -> Duration {
+        let delay = Duration::from_millis(self.delay_ms);
+        if !self.backoff {
+            return delay;
+        }
+        let exponent = u32::from(attempt.saturating_sub(1)).min(16);
+        delay.saturating_mul(1 << exponent).min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Characters a shell would interpret; rejected in option values because
+/// generated unit files and `show-cmd` output end up in shells.
+const SHELL_METACHARACTERS: &[char] = &[';', '|', '&', '$', '`', '<', '>', '(', ')', '\'', '"', '\\', '\n', '\r'];
+
+/// Makes ssh fail instead of prompting for a password or passphrase.
+pub const BATCH_MODE_OPTION: &str = "BatchMode=yes";
+
+/// Whether `forward` already tells ssh to never prompt.
+pub fn uses_batch_mode(forward: &PortForward) -> bool {
+    forward.ssh_options.iter().any(|option| option.eq_ignore_ascii_case(BATCH_MODE_OPTION))
+}
+
+/// Validate an ssh `-o` option given as `KEY=VALUE`.
+pub fn parse_ssh_option(option: &str) -> Result<String, String> {
+    let Some((key, value)) = option.split_once('=') else {
+        return Err(format!("'{}' is not in KEY=VALUE form", option));
+    };
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("'{}' is not a valid ssh option name", key));
+    }
+    if value.is_empty() {
+        return Err(format!("option '{}' has an empty value", key));
+    }
+    if let Some(c) = value.chars().find(|c| SHELL_METACHARACTERS.contains(c)) {
+        return Err(format!("option '{}' value contains shell metacharacter {:?}", key, c));
+    }
+    Ok(option.to_string())
+}
+
+/// Move the previous run's output to `<name>.1` so each start begins with an empty log.
+fn rotate_log(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .context("Failed to create log directory")?;
+    }
+    if fs::metadata(path).is_ok_and(|m| m.len() > 0) {
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(".1");
+        fs::rename(path, rotated)
+            .context("Failed to rotate log file")?;
+    }
+    Ok(())
+}
+
+fn open_log(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))
+}
+
+/// The `ControlPath` template of multiplexed forwards; ssh expands the `%` tokens
+/// so every user, host, and port gets its own socket.
+fn control_path(dir: &Path) -> String {
+    dir.join("%r@%h:%p").to_string_lossy().into_owned()
+}
+
+/// `-o` options that make a multiplexed forward share one connection per host.
+/// Empty when multiplexing is off or there is no config directory to hold the socket.
+fn control_args(forward: &PortForward) -> Vec<String> {
+    let Some(dir) = Config::control_dir().ok().filter(|_| forward.multiplex) else {
+        return Vec::new();
+    };
+    vec![
+        "-o".to_string(),
+        "ControlMaster=auto".to_string(),
+        "-o".to_string(),
+        format!("ControlPath={}", control_path(&dir)),
+        "-o".to_string(),
+        format!("ControlPersist={}", CONTROL_PERSIST),
+    ]
+}
+
+fn create_control_dir() -> Result<()> {
+    let dir = Config::control_dir()?;
+    fs::create_dir_all(&dir)
+        .context("Failed to create control socket directory")?;
+    // Anyone who can open a control socket can run commands on the remote host.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))
+            .context("Failed to restrict control socket directory")?;
+    }
+    Ok(())
+}
+
+/// Arguments for `ssh -O <operation>` (plus `extra`) sent to the shared master
+/// connection behind `forward`, or `None` without a config directory.
+fn master_control_args(forward: &PortForward, operation: &str, extra: &[String]) -> Option<Vec<String>> {
+    let dir = Config::control_dir().ok()?;
+    let mut args = vec![
+        "-O".to_string(),
+        operation.to_string(),
+        "-o".to_string(),
+        format!("ControlPath={}", control_path(&dir)),
+    ];
+    args.extend_from_slice(extra);
+    if let Some(ssh_port) = forward.ssh_port {
+        args.push("-p".to_string());
+        args.push(ssh_port.to_string());
+    }
+    args.push(connect_target(forward));
+    Some(args)
+}
+
+/// Arguments that make the master drop `forward`'s `-L` listener, which it
+/// keeps after the forward's own ssh process is gone.
+pub fn cancel_forward_args(forward: &PortForward) -> Option<Vec<String>> {
+    master_control_args(forward, "cancel", &["-L".to_string(), forward_spec(forward)])
+}
+
+fn run_master_command(args: Option<Vec<String>>) -> bool {
+    let Some(args) = args else {
+        return false;
+    };
+    Command::new("ssh")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Ask the shared master connection behind a multiplexed forward to exit, which
+/// also removes its control socket. Returns whether a master was running.
+pub fn close_master(forward: &PortForward) -> bool {
+    run_master_command(master_control_args(forward, "exit", &[]))
+}
+
+/// Ask the shared master to stop listening on `forward`'s local port while it
+/// keeps serving other forwards. Returns whether the master did so.
+pub fn cancel_forward(forward: &PortForward) -> bool {
+    run_master_command(cancel_forward_args(forward))
+}
+
+/// The `-L` value for `forward`: `[bind_address:]port:host:hostport`.
+fn forward_spec(forward: &PortForward) -> String {
+    format!("{}:{}:{}", forward.local_end(), forward.remote_host(), forward.remote_port)
+}
+
+/// The target ssh connects to, with environment variables expanded. An unset
+/// variable leaves the template in place; [`SshTunnel::start`] rejects that up front.
+fn connect_target(forward: &PortForward) -> String {
+    forward.connect_target().unwrap_or_else(|_| forward.target())
+}
+
+/// Locate the `ssh` binary on `$PATH`.
+pub fn find_ssh_binary() -> Option<PathBuf> {
+    port::find_binary("ssh")
+}
+
+/// Append the end of the tunnel log to an error message, if there is one.
+fn with_log_tail(mut message: String, log_file: Option<&Path>) -> String {
+    if let Some(path) = log_file {
+        for line in read_log_tail(path, 5).unwrap_or_default() {
+            message.push_str("\n  ");
+            message.push_str(&line);
+        }
+    }
+    message
+}
+
+/// The last `lines` lines of a tunnel log.
+pub fn read_log_tail(path: &Path, lines: usize) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read log file {}", path.display()))?;
+    let all: Vec<&str> = contents.lines().collect();
+    let start = all.len().saturating_sub(lines);
+    Ok(all[start..].iter().map(|l| l.to_string()).collect())
+}
 
 pub struct SshTunnel {
     process: Child,
+    command_line: String,
+    /// What was spawned, kept so [`SshTunnel::reconnect`] can spawn it again
+    program: OsString,
+    args: Vec<OsString>,
+    log_file: Option<PathBuf>,
+    local: SocketAddr,
+    startup_wait: Duration,
+}
+
+/// Spawn `command` with its stderr going to `log_file`, or inherited without one.
+fn spawn(command: &mut Command, log_file: Option<&Path>) -> Result<Child> {
+    let stderr = match log_file {
+        Some(path) => Stdio::from(open_log(path)?),
+        None => Stdio::inherit(),
+    };
+    command
+        .stderr(stderr)
+        .spawn()
+        .context("Failed to start ssh process")
 }
 
 impl SshTunnel {
+    /// Spawn ssh for `forward` and wait until its local port accepts connections.
+    /// Each attempt watches ssh for up to `startup_wait`: an early exit counts as a
+    /// failed attempt, while still running (or already listening) counts as alive.
+    /// Environment variables in the host and user are expanded now, at connect
+    /// time; the forward itself keeps them templated.
     pub fn start(
-        host: &str,
-        local_port: u16,
-        remote_port: u16,
+        forward: &PortForward,
+        ready_timeout: Duration,
+        retry: SshRetryPolicy,
+        startup_wait: Duration,
+        out: &OutputConfig,
     ) -> Result<Self> {
-        let forward_arg = format!("{}:localhost:{}", local_port, remote_port);
-        println!("Starting SSH Tunnel: ssh -N -L {} {}", forward_arg, host);
+        forward.connect_target()?;
+        let local = forward.local_addr();
+        let log_file = forward.log_file.as_deref().filter(|_| forward.log_enabled);
+        let command_line = Self::build_command_line(forward);
+        info!(out, "Starting SSH Tunnel: {}", command_line);
+
+        if let Some(path) = log_file {
+            rotate_log(path)?;
+        }
+        if forward.multiplex {
+            create_control_dir()?;
+        }
 
-        let mut process = Command::new("ssh")
-            .arg("-N")
-            .arg("-L")
-            .arg(&forward_arg)
-            .arg(host)
-            .spawn()
-            .context("Failed to start ssh process")?;
+        let mut command = Self::command_for(forward);
+        let started = Instant::now();
+        let mut attempt = 0;
+        let process = loop {
+            let mut process = spawn(&mut command, log_file)?;
 
-        std::thread::sleep(std::time::Duration::from_millis(500));
+            match Self::wait_for_startup(&mut process, local, startup_wait)? {
+                None => break process,
+                Some(status) if attempt >= retry.attempts => {
+                    let message = format!(
+                        "SSH process exited immediately: {:?} (gave up after {} attempt(s), {:.1}s)",
+                        status,
+                        attempt + 1,
+                        started.elapsed().as_secs_f32()
+                    );
+                    anyhow::bail!(with_log_tail(message, log_file));
+                }
+                Some(status) => {
+                    attempt += 1;
+                    let delay = retry.delay(attempt);
+                    info!(out, "SSH process exited ({:?}), retrying in {}ms ({}/{})",
+                          status, delay.as_millis(), attempt, retry.attempts);
+                    std::thread::sleep(delay);
+                }
+            }
+        };
 
-        if let Some(status) = process.try_wait()? {
-            anyhow::bail!("SSH process exited immediately: {:?}", status);
+        // ssh gives up on its own after ConnectTimeout, so don't wait much longer than that.
+        let ready_timeout = match forward.connect_timeout {
+            Some(secs) => ready_timeout.min(Duration::from_secs(secs) + CONNECT_TIMEOUT_GRACE),
+            None => ready_timeout,
+        };
+
+        // Without a terminal nobody can answer a prompt, so a silent ssh is most
+        // likely stuck on one.
+        let may_be_prompting = !uses_batch_mode(forward) && !std::io::stdin().is_terminal();
+        let mut tunnel = SshTunnel {
+            process,
+            command_line,
+            program: command.get_program().to_owned(),
+            args: command.get_args().map(ToOwned::to_owned).collect(),
+            log_file: log_file.map(Path::to_path_buf),
+            local,
+            startup_wait,
+        };
+        tunnel.wait_until_ready(local, ready_timeout, may_be_prompting)
+            .map_err(|e| anyhow::anyhow!(with_log_tail(format!("{:#}", e), log_file)))?;
+
+        Ok(tunnel)
+    }
+
+    /// Poll a just-spawned ssh until it exits, its forward starts listening, or
+    /// `startup_wait` elapses. Returns the exit status if it exited.
+    fn wait_for_startup(
+        process: &mut Child,
+        local: SocketAddr,
+        startup_wait: Duration,
+    ) -> Result<Option<std::process::ExitStatus>> {
+        let deadline = Instant::now() + startup_wait;
+        loop {
+            if let Some(status) = process.try_wait()? {
+                return Ok(Some(status));
+            }
+            if Instant::now() >= deadline || TcpStream::connect(local).is_ok() {
+                return Ok(None);
+            }
+            std::thread::sleep(STARTUP_POLL_INTERVAL);
+        }
+    }
+
+    /// Arguments passed to `ssh` (excluding the program name) for `forward`.
+    pub fn args(forward: &PortForward) -> Vec<String> {
+        let mut args = vec![
+            "-N".to_string(),
+            "-L".to_string(),
+            forward_spec(forward),
+        ];
+        if let Some(ssh_port) = forward.ssh_port {
+            args.push("-p".to_string());
+            args.push(ssh_port.to_string());
+        }
+        if let Some(secs) = forward.connect_timeout {
+            args.push("-o".to_string());
+            args.push(format!("ConnectTimeout={}", secs));
         }
+        if forward.gateway {
+            args.push("-o".to_string());
+            args.push("GatewayPorts=yes".to_string());
+        }
+        args.extend(control_args(forward));
+        for option in &forward.ssh_options {
+            args.push("-o".to_string());
+            args.push(option.clone());
+        }
+        args.push(connect_target(forward));
+        args
+    }
+
+    /// Arguments for an interactive `ssh` session to the host behind `forward`:
+    /// the same port and options as the tunnel, without the forwarding. A
+    /// multiplexed forward's session rides on its master connection.
+    pub fn shell_args(forward: &PortForward) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(ssh_port) = forward.ssh_port {
+            args.push("-p".to_string());
+            args.push(ssh_port.to_string());
+        }
+        if let Some(secs) = forward.connect_timeout {
+            args.push("-o".to_string());
+            args.push(format!("ConnectTimeout={}", secs));
+        }
+        args.extend(control_args(forward));
+        for option in &forward.ssh_options {
+            args.push("-o".to_string());
+            args.push(option.clone());
+        }
+        args.push(connect_target(forward));
+        args
+    }
 
-        Ok(SshTunnel { process })
+    /// The `ssh` command for `forward`, fully configured but not spawned, so
+    /// callers can inspect or run it themselves. `start` spawns exactly this.
+    pub fn command_for(forward: &PortForward) -> Command {
+        let mut command = Command::new("ssh");
+        command.args(Self::args(forward));
+        command
     }
+
+    /// The `ssh` invocation that would be used to establish a forward.
+    pub fn build_command_line(forward: &PortForward) -> String {
+        let command = Self::command_for(forward);
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Poll the local end of the tunnel until it accepts connections.
+    /// On failure the tunnel is dropped, which kills the ssh process.
+    fn wait_until_ready(&mut self, local: SocketAddr, timeout: Duration, may_be_prompting: bool) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = self.process.try_wait()? {
+                anyhow::bail!("SSH process exited before the tunnel was ready: {:?}", status);
+            }
+            if TcpStream::connect(local).is_ok() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                if may_be_prompting {
+                    anyhow::bail!(
+                        "ssh is still running but {} was not listening after {}s; it is \
+                         probably waiting for a password or passphrase that cannot be entered without \
+                         a terminal. Set up key-based authentication (ssh-agent, ssh-copy-id) or pass \
+                         --batch (`-o {}`) to fail fast instead of prompting",
+                        local,
+                        timeout.as_secs_f32(),
+                        BATCH_MODE_OPTION
+                    );
+                }
+                anyhow::bail!(
+                    "Tunnel did not start listening on {} within {}s \
+                     (ssh may be waiting for a password or the host is unreachable; \
+                     --batch makes it fail instead of prompting)",
+                    local,
+                    timeout.as_secs_f32()
+                );
+            }
+            std::thread::sleep(READY_POLL_INTERVAL);
+        }
+    }
+
     pub fn pid(&self) -> u32 {
         self.process.id()
     }
+
+    /// The exact command line that was spawned.
+    pub fn command_line(&self) -> &str {
+        &self.command_line
+    }
+
+    /// Whether ssh has exited, without blocking.
+    pub fn has_exited(&mut self) -> bool {
+        !matches!(self.process.try_wait(), Ok(None))
+    }
+
+    /// Stop ssh gracefully, escalating to a kill, and reap it.
+    fn stop(&mut self) {
+        let pid = self.process.id();
+        let process = &mut self.process;
+        if port::terminate_with(pid, port::TERMINATE_GRACE, || !matches!(process.try_wait(), Ok(None))).is_err() {
+            let _ = self.process.kill();
+        }
+        let _ = self.process.wait();
+    }
+
+    /// Restart ssh in place with the command it was started with, without
+    /// going back to the config. Works whether or not ssh is still running.
+    /// Fails if the new ssh exits within the startup wait; returns its pid.
+    pub fn reconnect(&mut self) -> Result<u32> {
+        self.stop();
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        let log_file = self.log_file.as_deref();
+        if let Some(path) = log_file {
+            rotate_log(path)?;
+        }
+        self.process = spawn(&mut command, log_file)?;
+        if let Some(status) = Self::wait_for_startup(&mut self.process, self.local, self.startup_wait)? {
+            let message = format!("SSH process exited immediately after reconnecting: {:?}", status);
+            anyhow::bail!(with_log_tail(message, log_file));
+        }
+        Ok(self.process.id())
+    }
 }
 
 impl Drop for SshTunnel {
+    /// Let ssh close its forwards cleanly, killing it only if it won't exit.
     fn drop(&mut self) {
-        let _ = self.process.kill();
-        let _ = self.process.wait();
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_args_keep_connection_settings_but_not_the_forward() {
+        let mut forward: PortForward = serde_json::from_str(
+            r#"{"id":"h","host":"h","user":"deploy","local_port":8080,"remote_port":80,"pid":null}"#,
+        ).unwrap();
+        forward.ssh_port = Some(2222);
+        forward.gateway = true;
+        forward.ssh_options = vec!["ProxyJump=bastion".to_string()];
+
+        assert_eq!(
+            SshTunnel::shell_args(&forward),
+            ["-p", "2222", "-o", "ProxyJump=bastion", "deploy@h"]
+        );
     }
 
+    #[test]
+    fn batch_mode_is_detected_case_insensitively() {
+        let mut forward: PortForward = serde_json::from_str(
+            r#"{"id":"h","host":"h","local_port":80,"remote_port":80,"pid":null}"#,
+        ).unwrap();
+        assert!(!uses_batch_mode(&forward));
+        forward.ssh_options = vec!["batchmode=YES".to_string()];
+        assert!(uses_batch_mode(&forward));
+        forward.ssh_options = vec!["BatchMode=no".to_string()];
+        assert!(!uses_batch_mode(&forward));
+    }
+
+    #[test]
+    fn retry_delay_doubles_only_with_backoff() {
+        let fixed = SshRetryPolicy::default();
+        assert_eq!(fixed.delay(3), Duration::from_secs(1));
+
+        let backoff = SshRetryPolicy { backoff: true, ..fixed };
+        let delays: Vec<u64> = (1..=7).map(|attempt| backoff.delay(attempt).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+    }
+
+    #[test]
+    fn parse_ssh_option_rejects_shell_metacharacters() {
+        assert!(parse_ssh_option("ControlPath=~/.ssh/cm-%r@%h:%p").is_ok());
+        assert!(parse_ssh_option("Compression=yes").is_ok());
+        assert!(parse_ssh_option("Compression").is_err());
+        assert!(parse_ssh_option("LocalCommand=rm -rf /; true").is_err());
+        assert!(parse_ssh_option("ProxyCommand=$(evil)").is_err());
+    }
+
+    #[test]
+    fn command_for_matches_args_without_spawning() {
+        let mut forward: PortForward = serde_json::from_str(
+            r#"{"id":"db","host":"bastion","user":"admin","local_port":15432,"remote_port":5432,"pid":null}"#,
+        ).unwrap();
+        forward.remote_host = Some("db.internal".to_string());
+        forward.ssh_port = Some(2222);
+
+        let command = SshTunnel::command_for(&forward);
+
+        assert_eq!(command.get_program(), "ssh");
+        let args: Vec<_> = command.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(args, SshTunnel::args(&forward));
+        assert_eq!(args[..3], ["-N", "-L", "15432:db.internal:5432"]);
+        assert_eq!(args.last().unwrap(), "admin@bastion");
+        assert_eq!(
+            SshTunnel::build_command_line(&forward),
+            "ssh -N -L 15432:db.internal:5432 -p 2222 admin@bastion"
+        );
+    }
+
+    #[test]
+    fn multiplexed_forwards_share_a_control_socket_per_host() {
+        let mut forward: PortForward = serde_json::from_str(
+            r#"{"id":"h","host":"h","local_port":8080,"remote_port":80,"pid":null}"#,
+        ).unwrap();
+        assert!(!SshTunnel::args(&forward).iter().any(|a| a.starts_with("Control")));
+
+        forward.multiplex = true;
+        let args = SshTunnel::args(&forward);
+        let path = control_path(&Config::control_dir().unwrap());
+        assert!(path.ends_with("%r@%h:%p"));
+        for option in ["ControlMaster=auto".to_string(), format!("ControlPath={}", path)] {
+            assert!(args.contains(&option), "missing {}", option);
+        }
+        assert!(args.iter().any(|a| a.starts_with("ControlPersist=")));
+        assert_eq!(args.last().unwrap(), "h");
+        assert!(forward.matches_command(&SshTunnel::build_command_line(&forward)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reconnect_respawns_whether_or_not_the_process_is_still_running() {
+        let mut command = Command::new("sleep");
+        command.arg("5");
+        let mut tunnel = SshTunnel {
+            process: spawn(&mut command, None).unwrap(),
+            command_line: "sleep 5".to_string(),
+            program: "sleep".into(),
+            args: vec!["5".into()],
+            log_file: None,
+            local: "127.0.0.1:9".parse().unwrap(),
+            startup_wait: Duration::from_millis(100),
+        };
+        let first = tunnel.pid();
+
+        let second = tunnel.reconnect().unwrap();
+        assert_ne!(second, first);
+        assert!(!tunnel.has_exited());
+
+        tunnel.process.kill().unwrap();
+        tunnel.process.wait().unwrap();
+        let third = tunnel.reconnect().unwrap();
+        assert_ne!(third, second);
+        assert_eq!(tunnel.pid(), third);
+        assert!(!tunnel.has_exited());
+
+        tunnel.args = vec!["not-a-duration".into()];
+        assert!(tunnel.reconnect().is_err());
+    }
+
+    #[test]
+    fn cancel_forward_args_name_the_listener_and_the_master() {
+        let mut forward: PortForward = serde_json::from_str(
+            r#"{"id":"db","host":"bastion","user":"admin","local_port":15432,"remote_port":5432,"pid":null}"#,
+        ).unwrap();
+        forward.remote_host = Some("db.internal".to_string());
+        forward.ssh_port = Some(2222);
+        forward.multiplex = true;
+
+        let args = cancel_forward_args(&forward).unwrap();
+        let path = control_path(&Config::control_dir().unwrap());
+
+        assert_eq!(args, [
+            "-O", "cancel", "-o", &format!("ControlPath={}", path),
+            "-L", "15432:db.internal:5432", "-p", "2222", "admin@bastion",
+        ]);
+        assert!(SshTunnel::args(&forward).contains(&args[5]));
+    }
 }
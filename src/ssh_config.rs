@@ -0,0 +1,192 @@
+use crate::config::PortForward;
+
+/// A concrete `Host` block from an OpenSSH client config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshHostEntry {
+    /// The alias after `Host`, which ssh resolves using the rest of the block
+    pub alias: String,
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl SshHostEntry {
+    fn new(alias: &str) -> Self {
+        SshHostEntry {
+            alias: alias.to_string(),
+            hostname: None,
+            user: None,
+            port: None,
+        }
+    }
+
+    /// `user@hostname:port` as ssh would connect, for display.
+    pub fn target(&self) -> String {
+        let mut target = String::new();
+        if let Some(user) = &self.user {
+            target.push_str(user);
+            target.push('@');
+        }
+        target.push_str(self.hostname.as_deref().unwrap_or(&self.alias));
+        if let Some(port) = self.port {
+            target.push_str(&format!(":{}", port));
+        }
+        target
+    }
+}
+
+/// Split `Key value` or `Key=value` into a lowercased key and its value.
+fn split_directive(line: &str) -> Option<(String, &str)> {
+    let split_at = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let (key, rest) = line.split_at(split_at);
+    let value = rest
+        .trim_start_matches(|c: char| c.is_whitespace())
+        .trim_start_matches('=')
+        .trim();
+    Some((key.to_ascii_lowercase(), value.trim_matches('"')))
+}
+
+/// Parse `Host` blocks, skipping wildcard patterns and `Match` blocks.
+/// Directives before the first `Host` line apply globally and are ignored.
+pub fn parse(contents: &str) -> Vec<SshHostEntry> {
+    let mut entries: Vec<SshHostEntry> = Vec::new();
+    // Indices into `entries` that the current block's directives apply to
+    let mut current: Vec<usize> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = split_directive(line) else {
+            continue;
+        };
+
+        match key.as_str() {
+            "host" => {
+                current.clear();
+                for alias in value.split_whitespace() {
+                    if alias.contains(['*', '?', '!']) {
+                        continue;
+                    }
+                    current.push(entries.len());
+                    entries.push(SshHostEntry::new(alias));
+                }
+            }
+            "match" => current.clear(),
+            "hostname" | "user" | "port" => {
+                for &index in &current {
+                    let entry = &mut entries[index];
+                    match key.as_str() {
+                        "hostname" => entry.hostname = Some(value.to_string()),
+                        "user" => entry.user = Some(value.to_string()),
+                        _ => entry.port = value.parse().ok(),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+/// Render `forward` as a `Host` block for an OpenSSH client config, so that
+/// `ssh -N <alias>` sets up the same tunnel.
+pub fn render_host_block(alias: &str, forward: &PortForward) -> String {
+    let target = forward.target();
+    let (user, hostname) = match target.rsplit_once('@') {
+        Some((user, hostname)) => (Some(user), hostname),
+        None => (None, target.as_str()),
+    };
+
+    let mut lines = Vec::new();
+    if let Some(comment) = &forward.comment {
+        lines.push(format!("# {}", comment));
+    }
+    lines.push(format!("Host {}", alias));
+    lines.push(format!("    HostName {}", hostname));
+    if let Some(user) = user {
+        lines.push(format!("    User {}", user));
+    }
+    if let Some(port) = forward.ssh_port {
+        lines.push(format!("    Port {}", port));
+    }
+    lines.push(format!(
+        "    LocalForward {} {}:{}",
+        forward.local_end(), forward.remote_host(), forward.remote_port
+    ));
+    if forward.gateway {
+        lines.push("    GatewayPorts yes".to_string());
+    }
+    if let Some(secs) = forward.connect_timeout {
+        lines.push(format!("    ConnectTimeout {}", secs));
+    }
+    let mut keeps_alive = false;
+    for option in &forward.ssh_options {
+        if let Some((key, value)) = option.split_once('=') {
+            keeps_alive |= key.eq_ignore_ascii_case("ServerAliveInterval");
+            lines.push(format!("    {} {}", key, value));
+        }
+    }
+    if !keeps_alive {
+        lines.push("    ServerAliveInterval 60".to_string());
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_forward_as_host_block() {
+        let json = r#"{"profiles": {"default": {"db": {
+            "id": "db", "host": "admin@bastion", "local_port": 8080, "remote_port": 5432,
+            "remote_host": "db.internal", "ssh_port": 2222, "pid": null
+        }}}}"#;
+        let config = crate::Config::from_json(json).unwrap();
+
+        let block = render_host_block("db", &config.forwards()["db"]);
+
+        assert_eq!(block, "\
+Host db
+    HostName bastion
+    User admin
+    Port 2222
+    LocalForward 8080 db.internal:5432
+    ServerAliveInterval 60
+");
+    }
+
+    #[test]
+    fn parses_host_blocks_and_skips_wildcards() {
+        let config = "\
+# global defaults
+ServerAliveInterval 30
+
+Host *
+    User ignored
+
+Host db db-alias
+    HostName db.internal
+    User admin
+    Port=2222
+
+Host web
+  hostname web.example.com
+
+Match host foo
+    User nobody
+";
+
+        let entries = parse(config);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].alias, "db");
+        assert_eq!(entries[0].target(), "admin@db.internal:2222");
+        assert_eq!(entries[1].alias, "db-alias");
+        assert_eq!(entries[1].port, Some(2222));
+        assert_eq!(entries[2].target(), "web.example.com");
+    }
+}
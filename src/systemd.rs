@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use crate::config::PortForward;
+use crate::ssh::SshTunnel;
+
+/// Quote one `ExecStart=` argument per systemd's command-line rules.
+fn quote_arg(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    if escaped.contains(char::is_whitespace) || escaped.is_empty() {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Render a service unit that runs the forward's ssh command as `user`.
+pub fn render_unit(forward: &PortForward, ssh_path: &Path, user: &str) -> String {
+    let exec_start = std::iter::once(ssh_path.display().to_string())
+        .chain(SshTunnel::args(forward))
+        .map(|arg| quote_arg(&arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        "\
+[Unit]
+Description=pfm port forward {id} (localhost:{local} -> {host}:{remote})
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=simple
+User={user}
+ExecStart={exec_start}
+Restart=on-failure
+RestartSec=5s
+
+[Install]
+WantedBy=multi-user.target
+",
+        id = forward.id,
+        local = forward.local_port,
+        host = forward.target(),
+        remote = forward.remote_port,
+        user = user,
+        exec_start = exec_start,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_arguments_with_spaces_and_specifiers() {
+        assert_eq!(quote_arg("-N"), "-N");
+        assert_eq!(quote_arg("ProxyCommand=nc %h %p"), "\"ProxyCommand=nc %%h %%p\"");
+    }
+}
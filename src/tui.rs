@@ -0,0 +1,172 @@
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Cell, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use pfm::config::{Config, PortForward};
+use pfm::manager;
+use pfm::output::OutputConfig;
+
+/// How often the list is reloaded when no key is pressed.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+const HELP: &str = "↑/k ↓/j select   s stop   r restart   q quit";
+
+enum Key {
+    Up,
+    Down,
+    Stop,
+    Restart,
+    Quit,
+}
+
+/// What one frame shows.
+struct View<'a> {
+    profile: &'a str,
+    forwards: &'a [PortForward],
+    running: &'a [bool],
+    selected: usize,
+    status: &'a str,
+}
+
+/// Full-screen view of the profile's forwards: reloads the config every
+/// [`REFRESH_INTERVAL`] and stops or restarts the selected forward on a keypress.
+pub fn run(profile: &str, ready_timeout: Duration, out: &OutputConfig) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        anyhow::bail!("pfm ui needs an interactive terminal");
+    }
+    // Also restores the terminal on panic
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, profile, ready_timeout, out);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    profile: &str,
+    ready_timeout: Duration,
+    out: &OutputConfig,
+) -> Result<()> {
+    let mut selected = 0;
+    let mut status = String::new();
+
+    loop {
+        let mut config = Config::load()?;
+        config.use_profile(profile);
+        let forwards: Vec<PortForward> = config.get_sorted_forwards().into_iter().cloned().collect();
+        let running = manager::running_states(&forwards.iter().collect::<Vec<_>>());
+        selected = selected.min(forwards.len().saturating_sub(1));
+        let mut view = View { profile, forwards: &forwards, running: &running, selected, status: &status };
+        terminal.draw(|frame| draw(frame, &view))?;
+
+        let Some(key) = read_key(REFRESH_INTERVAL)? else {
+            continue;
+        };
+        let current = forwards.get(selected).map(|f| f.id.clone());
+        match (key, current) {
+            (Key::Quit, _) => return Ok(()),
+            (Key::Up, _) => selected = selected.saturating_sub(1),
+            (Key::Down, _) => selected = (selected + 1).min(forwards.len().saturating_sub(1)),
+            (Key::Stop, Some(id)) => {
+                status = match manager::stop_forward(&mut config, &id, out).and_then(|_| config.save()) {
+                    Ok(()) => format!("Stopped {}", id),
+                    Err(e) => format!("Failed to stop {}: {:#}", id, e),
+                };
+            }
+            (Key::Restart, Some(id)) => {
+                let restarting = format!("Restarting {}...", id);
+                view.status = &restarting;
+                terminal.draw(|frame| draw(frame, &view))?;
+                status = match restart(&mut config, &id, ready_timeout, out) {
+                    Ok(pid) => format!("Restarted {} (PID: {})", id, pid),
+                    Err(e) => format!("Failed to restart {}: {:#}", id, e),
+                };
+            }
+            (Key::Stop | Key::Restart, None) => {}
+        }
+    }
+}
+
+fn restart(config: &mut Config, id: &str, ready_timeout: Duration, out: &OutputConfig) -> Result<u32> {
+    manager::stop_forward(config, id, out)?;
+    let pid = manager::relaunch_forward(config, id, ready_timeout, out);
+    config.save()?;
+    pid
+}
+
+fn draw(frame: &mut Frame, view: &View) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+
+    frame.render_widget(Paragraph::new(vec![
+        Line::from(vec!["pfm ui".bold(), format!(" (profile {})", view.profile).dim()]),
+        Line::from(HELP.dim()),
+    ]), header);
+
+    if view.forwards.is_empty() {
+        frame.render_widget(Paragraph::new("No port forwards configured".dim()), body);
+    } else {
+        let rows = view.forwards.iter().zip(view.running).enumerate().map(|(index, (forward, &running))| {
+            let state = if running { "● running".green() } else { "○ stopped".yellow() };
+            let remote = match &forward.remote_host {
+                Some(remote_host) => format!("{} → {}:{}", forward.target(), remote_host, forward.remote_port),
+                None => format!("{}:{}", forward.target(), forward.remote_port),
+            };
+            Row::new(vec![
+                Cell::from(index.to_string()),
+                Cell::from(state),
+                Cell::from(forward.id.as_str()),
+                Cell::from(format!("localhost:{}", forward.local_port)),
+                Cell::from(remote),
+            ])
+        });
+        let widths = [
+            Constraint::Length(3),
+            Constraint::Length(9),
+            Constraint::Min(24),
+            Constraint::Length(15),
+            Constraint::Min(24),
+        ];
+        let table = Table::new(rows, widths)
+            .header(Row::new(["#", "STATUS", "ID", "LOCAL", "REMOTE"]).bold())
+            .row_highlight_style(Style::new().reversed())
+            .highlight_symbol("> ");
+        let mut state = TableState::new().with_selected(Some(view.selected));
+        frame.render_stateful_widget(table, body, &mut state);
+    }
+
+    frame.render_widget(Paragraph::new(view.status), footer);
+}
+
+/// Wait up to `timeout` for a keypress.
+fn read_key(timeout: Duration) -> Result<Option<Key>> {
+    if !event::poll(timeout)? {
+        return Ok(None);
+    }
+    // Windows also reports key releases
+    let Event::Key(key) = event::read()? else {
+        return Ok(None);
+    };
+    if key.kind != KeyEventKind::Press {
+        return Ok(None);
+    }
+    Ok(match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Quit),
+        KeyCode::Char('q' | 'Q') | KeyCode::Esc => Some(Key::Quit),
+        KeyCode::Char('k') | KeyCode::Up => Some(Key::Up),
+        KeyCode::Char('j') | KeyCode::Down => Some(Key::Down),
+        KeyCode::Char('s') => Some(Key::Stop),
+        KeyCode::Char('r') => Some(Key::Restart),
+        _ => None,
+    })
+}